@@ -0,0 +1,364 @@
+use crate::{ConversionError, Resource};
+use std::fmt;
+use std::str::FromStr;
+
+/// Operating systems release assets are built for
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Os {
+    Linux,
+    Macos,
+    Windows,
+    FreeBsd,
+}
+
+/// CPU architectures release assets are built for
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+    X86,
+    Arm,
+    Riscv64,
+}
+
+const OSES: [Os; 4] = [Os::Linux, Os::Macos, Os::Windows, Os::FreeBsd];
+const ARCHES: [Arch; 5] = [
+    Arch::X86_64,
+    Arch::Aarch64,
+    Arch::X86,
+    Arch::Arm,
+    Arch::Riscv64,
+];
+
+impl Os {
+    /// Words used for the OS in asset names, e.g. `darwin` for macOS
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Os::Linux => &["linux"],
+            Os::Macos => &["darwin", "macos", "apple", "osx", "mac"],
+            Os::Windows => &["windows", "win", "win64", "win32", "msvc"],
+            Os::FreeBsd => &["freebsd"],
+        }
+    }
+}
+
+impl Arch {
+    /// Words used for the architecture in asset names, e.g. `amd64` for x86_64
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Arch::X86_64 => &["x86_64", "x86-64", "amd64", "x64", "win64"],
+            Arch::Aarch64 => &["aarch64", "arm64"],
+            Arch::X86 => &["i686", "i586", "i386", "386", "x86", "win32"],
+            Arch::Arm => &["armv7", "armv7l", "armhf", "armv6", "arm"],
+            Arch::Riscv64 => &["riscv64", "riscv64gc"],
+        }
+    }
+}
+
+/// Platform to pick release assets for
+///
+/// Parsed from a target triple such as `x86_64-unknown-linux-musl` or an
+/// OS/arch pair such as `linux-amd64` or `darwin/arm64`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Target {
+    pub os: Os,
+    pub arch: Arch,
+    /// ABI such as `musl`, `gnu` or `msvc`, if known
+    pub env: Option<String>,
+}
+
+impl Target {
+    /// The platform this program runs on, None if it isn't recognized
+    pub fn current() -> Option<Target> {
+        let os = match std::env::consts::OS {
+            "linux" => Os::Linux,
+            "macos" => Os::Macos,
+            "windows" => Os::Windows,
+            "freebsd" => Os::FreeBsd,
+            _ => return None,
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => Arch::X86_64,
+            "aarch64" => Arch::Aarch64,
+            "x86" => Arch::X86,
+            "arm" => Arch::Arm,
+            "riscv64" => Arch::Riscv64,
+            _ => return None,
+        };
+        let env = if cfg!(target_env = "musl") {
+            Some("musl")
+        } else if cfg!(target_env = "gnu") {
+            Some("gnu")
+        } else if cfg!(target_env = "msvc") {
+            Some("msvc")
+        } else {
+            None
+        };
+        Some(Target {
+            os,
+            arch,
+            env: env.map(|env| env.to_string()),
+        })
+    }
+}
+
+impl FromStr for Target {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        let os = OSES
+            .into_iter()
+            .find(|os| os.aliases().iter().any(|alias| has_word(&name, alias)));
+        let arch = ARCHES.into_iter().find(|arch| matches_arch(&name, *arch));
+        let env = ["musl", "gnu", "msvc"].into_iter().find(|env| {
+            name.split(['-', '_', '/'])
+                .any(|part| part.starts_with(env))
+        });
+
+        match (os, arch) {
+            (Some(os), Some(arch)) => Ok(Target {
+                os,
+                arch,
+                env: env.map(|env| env.to_string()),
+            }),
+            _ => Err(ConversionError::InvalidTarget(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Os {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Os::Linux => "linux",
+            Os::Macos => "macos",
+            Os::Windows => "windows",
+            Os::FreeBsd => "freebsd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.aliases()[0])
+    }
+}
+
+/// How to pick an asset: by platform, or by a glob over the asset name
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum AssetSelector {
+    Target(Target),
+    /// `*` matches any run of characters, `?` a single one
+    Glob(String),
+}
+
+impl FromStr for AssetSelector {
+    type Err = ConversionError;
+
+    /// Patterns containing `*` or `?` are globs, anything else a target
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(['*', '?']) {
+            return Ok(AssetSelector::Glob(s.to_string()));
+        }
+        Ok(AssetSelector::Target(Target::from_str(s)?))
+    }
+}
+
+/// Pick the release asset that best fits `selector` among `names`
+///
+/// For a target, assets built for another OS or architecture are ruled
+/// out, and so are assets naming no platform, like source tarballs. The
+/// rest are ranked by how well they name the platform, the ABI and
+/// the archive format usual for the OS, e.g. `.tar.gz` on Linux and `.zip`
+/// on Windows. Checksums, signatures and other metadata are never picked.
+/// For a glob the first matching name wins.
+pub fn select_asset<S: AsRef<str>>(
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    names: &[S],
+    selector: &AssetSelector,
+) -> Option<Resource> {
+    let names = names.iter().map(|name| name.as_ref());
+    let name = match selector {
+        AssetSelector::Glob(pattern) => names.into_iter().find(|name| glob(pattern, name))?,
+        AssetSelector::Target(target) => {
+            let mut best: Option<(i32, &str)> = None;
+            for name in names {
+                // Earlier assets win ties
+                if let Some(score) = score(name, target)
+                    && best.is_none_or(|(best, _)| score > best)
+                {
+                    best = Some((score, name));
+                }
+            }
+            best?.1
+        }
+    };
+    Some(Resource::release(
+        owner.to_string(),
+        repo.to_string(),
+        tag.to_string(),
+        name.to_string(),
+    ))
+}
+
+/// How well an asset name fits the target, None if it can't run there
+fn score(name: &str, target: &Target) -> Option<i32> {
+    let name = name.to_ascii_lowercase();
+    const METADATA: [&str; 12] = [
+        ".sha256",
+        ".sha256sum",
+        ".sha512",
+        ".md5",
+        ".sig",
+        ".asc",
+        ".pem",
+        ".sbom",
+        ".json",
+        ".txt",
+        ".intoto.jsonl",
+        ".blockmap",
+    ];
+    if METADATA.iter().any(|ext| name.ends_with(ext)) || name.contains("checksums") {
+        return None;
+    }
+
+    let mut score = 0;
+    // Source tarballs and the like name no platform at all
+    let mut named = false;
+
+    let os = OSES
+        .into_iter()
+        .filter(|os| os.aliases().iter().any(|alias| has_word(&name, alias)))
+        .collect::<Vec<_>>();
+    if os.contains(&target.os) {
+        score += 10;
+        named = true;
+    } else if !os.is_empty() {
+        return None;
+    }
+
+    let arches = ARCHES
+        .into_iter()
+        .filter(|arch| matches_arch(&name, *arch))
+        .collect::<Vec<_>>();
+    if arches.contains(&target.arch) {
+        score += 10;
+        named = true;
+    } else if target.os == Os::Macos && has_word(&name, "universal") {
+        score += 8;
+        named = true;
+    } else if !arches.is_empty() {
+        return None;
+    }
+
+    score += match (target.env.as_deref(), env_of(&name)) {
+        (Some(wanted), Some(found)) if wanted == found => 3,
+        // Static musl builds run everywhere, prefer them over other ABIs
+        (_, Some("musl")) => 1,
+        (_, Some(_)) => -1,
+        (_, None) => 0,
+    };
+
+    let format = match target.os {
+        Os::Windows => [
+            (".zip", 3),
+            (".exe", 2),
+            (".7z", 1),
+            (".msi", 1),
+            (".tar.gz", 1),
+        ]
+        .iter()
+        .find(|(ext, _)| name.ends_with(ext))
+        .map_or(0, |(_, points)| *points),
+        _ => [
+            (".tar.gz", 3),
+            (".tgz", 3),
+            (".tar.xz", 3),
+            (".tar.bz2", 2),
+            (".tar.zst", 2),
+            (".zip", 1),
+            (".appimage", 1),
+        ]
+        .iter()
+        .find(|(ext, _)| name.ends_with(ext))
+        .map_or(0, |(_, points)| *points),
+    };
+    score += format;
+
+    // Packages and installers belong to a specific distribution or OS
+    const PACKAGES: [(&str, Os); 6] = [
+        (".deb", Os::Linux),
+        (".rpm", Os::Linux),
+        (".apk", Os::Linux),
+        (".dmg", Os::Macos),
+        (".pkg", Os::Macos),
+        (".msi", Os::Windows),
+    ];
+    if let Some((_, os)) = PACKAGES.iter().find(|(ext, _)| name.ends_with(ext)) {
+        if *os != target.os {
+            return None;
+        }
+        score -= 2;
+        named = true;
+    }
+    if target.os == Os::Windows && name.ends_with(".exe") {
+        named = true;
+    }
+
+    named.then_some(score)
+}
+
+fn env_of(name: &str) -> Option<&'static str> {
+    ["musl", "gnu", "msvc"].into_iter().find(|env| {
+        name.split(['-', '_', '.'])
+            .any(|part| part.starts_with(env))
+    })
+}
+
+fn matches_arch(name: &str, arch: Arch) -> bool {
+    // x86 must not match the x86 of x86_64
+    if arch == Arch::X86 && matches_arch(name, Arch::X86_64) {
+        let without = name.replace("x86_64", "").replace("x86-64", "");
+        return arch.aliases().iter().any(|alias| has_word(&without, alias));
+    }
+    arch.aliases().iter().any(|alias| has_word(name, alias))
+}
+
+/// Whether `word` appears in `text` delimited by non-alphanumeric characters
+fn has_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// Match `text` against a pattern of `*` and `?` wildcards
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((after, tried)) = star {
+            p = after;
+            t = tried + 1;
+            star = Some((after, tried + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
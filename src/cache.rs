@@ -0,0 +1,518 @@
+use crate::checksum::sha256_file;
+use crate::download::{agent, check_downloadable, describe, lfs_pointer_error};
+use crate::{ConversionError, LfsPointer, Proxy, ProxyChain, Resource};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds release assets under tags like `nightly` are served without revalidation
+const MOVING_RELEASE_TTL: u64 = 10 * 60;
+
+/// Response headers kept with each entry besides the ETag
+const STORED_HEADERS: [&str; 3] = ["Content-Type", "Last-Modified", "Content-Disposition"];
+
+/// Content-addressed store of downloaded resources
+///
+/// Bodies are stored once per SHA-256 under `blobs/`, and `index/` maps the
+/// canonical URL of each resource to its blob. Resources pinned to a commit
+/// and assets of version releases are served from disk without asking
+/// upstream. Assets of other releases, such as `nightly`, are revalidated
+/// with their ETag once they are ten minutes old, others on every use.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+/// A cached resource
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CacheEntry {
+    /// Canonical GitHub URL of the resource
+    pub url: String,
+    /// SHA-256 of the body, naming its blob
+    pub sha256: String,
+    pub etag: Option<String>,
+    /// Served without revalidation
+    pub immutable: bool,
+    /// Unix time until which the entry is served without revalidation
+    pub fresh_until: Option<u64>,
+    /// Unix time of the last use
+    pub used: u64,
+    pub size: u64,
+    /// Response headers describing the body, such as `Content-Type`
+    pub headers: Vec<(String, String)>,
+}
+
+/// Result of [`Cache::fetch`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cached {
+    /// Cached body, don't modify it
+    pub path: PathBuf,
+    /// Proxy that served or revalidated the body, None if no request was made
+    pub proxy: Option<Proxy>,
+    /// Response headers describing the body, including the `ETag`
+    pub headers: Vec<(String, String)>,
+}
+
+/// What [`Cache::lookup`] found for a resource that needs a request
+struct Lookup {
+    url: String,
+    index: PathBuf,
+    entry: Option<CacheEntry>,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache {
+            dir: dir.into(),
+            max_size: None,
+        }
+    }
+
+    /// Default location: `<cache dir>/github-proxy/files`
+    pub fn open_default() -> Option<Cache> {
+        Some(Cache::new(
+            crate::ranking::cache_dir()?
+                .join("github-proxy")
+                .join("files"),
+        ))
+    }
+
+    /// Evict least recently used entries once the cache grows beyond `max_size` bytes
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get a resource from the cache, downloading it through the chain if needed
+    ///
+    /// Proxies are tried in order like [`crate::fetch`]. Mutable resources
+    /// are revalidated, and kept if upstream answers 304 Not Modified.
+    pub fn fetch(
+        &self,
+        resource: &Resource,
+        chain: &ProxyChain,
+    ) -> Result<Cached, ConversionError> {
+        self.fetch_from(resource, &agent(chain), chain.urls(resource), None)
+    }
+
+    /// Like [`Cache::fetch`], trying the given proxied URLs in order
+    ///
+    /// Bodies larger than `max_size` are not stored and fail with
+    /// [`ConversionError::TooLarge`].
+    pub(crate) fn fetch_from(
+        &self,
+        resource: &Resource,
+        agent: &ureq::Agent,
+        urls: Vec<(Proxy, String)>,
+        max_size: Option<u64>,
+    ) -> Result<Cached, ConversionError> {
+        let lookup = match self.lookup(resource)? {
+            Ok(hit) => return Ok(hit),
+            Err(lookup) => lookup,
+        };
+        let mut attempts = Vec::new();
+        for (proxy, proxied) in urls {
+            match self.download(resource, agent, &lookup, (proxy, &proxied), max_size) {
+                Err(ConversionError::UpstreamStatus(status)) => {
+                    attempts.push((proxy, format!("{}: status {}", proxied, status)));
+                }
+                Err(ConversionError::DownloadFailed(failed)) => attempts.extend(failed),
+                result => return result,
+            }
+        }
+        Err(ConversionError::DownloadFailed(attempts))
+    }
+
+    /// Like [`Cache::fetch_from`] with a single URL, keeping the status of upstream
+    ///
+    /// Error statuses are returned as [`ConversionError::UpstreamStatus`].
+    #[cfg(feature = "server")]
+    pub(crate) fn fetch_via(
+        &self,
+        resource: &Resource,
+        agent: &ureq::Agent,
+        (proxy, proxied): (Proxy, &str),
+        max_size: Option<u64>,
+    ) -> Result<Cached, ConversionError> {
+        match self.lookup(resource)? {
+            Ok(hit) => Ok(hit),
+            Err(lookup) => self.download(resource, agent, &lookup, (proxy, proxied), max_size),
+        }
+    }
+
+    /// Serve an entry that needs no revalidation, or else return what a download needs to know
+    fn lookup(&self, resource: &Resource) -> Result<Result<Cached, Lookup>, ConversionError> {
+        check_downloadable(resource)?;
+        let url = resource
+            .url(&Proxy::Github)
+            .ok_or_else(|| ConversionError::InvalidUrl(format!("{:?}", resource)))?;
+        let index = self.index_path(&url);
+        let entry = read_entry(&index).filter(|entry| self.blob_path(&entry.sha256).exists());
+
+        if let Some(entry) = &entry
+            && (entry.immutable || entry.fresh_until.is_some_and(|fresh| now() < fresh))
+        {
+            self.touch(&index, entry)?;
+            return Ok(Ok(self.cached(entry, None)));
+        }
+        Ok(Err(Lookup { url, index, entry }))
+    }
+
+    /// Download and store a body, or revalidate the cached one
+    fn download(
+        &self,
+        resource: &Resource,
+        agent: &ureq::Agent,
+        lookup: &Lookup,
+        (proxy, proxied): (Proxy, &str),
+        max_size: Option<u64>,
+    ) -> Result<Cached, ConversionError> {
+        let failed = |e: String| {
+            ConversionError::DownloadFailed(vec![(proxy, format!("{}: {}", proxied, e))])
+        };
+        let cached = lookup.entry.as_ref();
+
+        let mut request = agent.get(proxied);
+        if let Some(etag) = cached.and_then(|entry| entry.etag.as_deref()) {
+            request = request.set("If-None-Match", etag);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(ConversionError::UpstreamStatus(status));
+            }
+            Err(e) => return Err(failed(describe(e))),
+        };
+
+        if let Some(entry) = cached
+            && response.status() == 304
+        {
+            let entry = CacheEntry {
+                fresh_until: fresh_until(resource),
+                ..entry.clone()
+            };
+            self.touch(&lookup.index, &entry)?;
+            return Ok(self.cached(&entry, Some(proxy)));
+        }
+
+        let length: Option<u64> = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok());
+        if let (Some(max), Some(length)) = (max_size, length)
+            && length > max
+        {
+            return Err(ConversionError::TooLarge(max));
+        }
+        let etag = response.header("ETag").map(|etag| etag.to_string());
+        let headers = STORED_HEADERS
+            .iter()
+            .filter_map(|name| Some((name.to_string(), response.header(name)?.to_string())))
+            .collect();
+        let (sha256, size) = match self.store(response.into_reader(), max_size) {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return Err(ConversionError::TooLarge(max_size.unwrap_or_default())),
+            Err(e) => return Err(failed(e.to_string())),
+        };
+        // The unreferenced blob is removed by the next garbage collection
+        if !proxy.resolves_lfs()
+            && size <= LfsPointer::MAX_SIZE
+            && let Some(pointer) = std::fs::read(self.blob_path(&sha256))
+                .ok()
+                .and_then(|content| LfsPointer::parse(&content))
+        {
+            return Err(failed(lfs_pointer_error(&pointer)));
+        }
+        let entry = CacheEntry {
+            url: lookup.url.clone(),
+            sha256,
+            etag,
+            immutable: resource.is_immutable(),
+            fresh_until: fresh_until(resource),
+            used: now(),
+            size,
+            headers,
+        };
+        write_entry(&lookup.index, &entry)?;
+        if let Some(max_size) = self.max_size {
+            self.evict(max_size, Some(&entry.url))?;
+        }
+        Ok(self.cached(&entry, Some(proxy)))
+    }
+
+    fn cached(&self, entry: &CacheEntry, proxy: Option<Proxy>) -> Cached {
+        let etag = entry
+            .etag
+            .as_ref()
+            .map(|etag| ("ETag".to_string(), etag.clone()));
+        Cached {
+            path: self.blob_path(&entry.sha256),
+            proxy,
+            headers: etag.into_iter().chain(entry.headers.clone()).collect(),
+        }
+    }
+
+    /// Every entry, most recently used first
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let dir = match std::fs::read_dir(self.dir.join("index")) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for file in dir {
+            if let Some(entry) = read_entry(&file?.path()) {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by(|a, b| b.used.cmp(&a.used).then_with(|| a.url.cmp(&b.url)));
+        Ok(entries)
+    }
+
+    /// Forget the entry for `resource`, deleting its blob unless another entry shares it
+    ///
+    /// Used when the content turned out to be wrong, so the next fetch asks
+    /// upstream again.
+    pub fn remove(&self, resource: &Resource) -> io::Result<()> {
+        if let Some(url) = resource.url(&Proxy::Github) {
+            remove_file(&self.index_path(&url))?;
+        }
+        self.collect_garbage(&self.entries()?)
+    }
+
+    /// Remove least recently used entries until the blobs take at most `max_size` bytes
+    ///
+    /// Returns the removed entries. Blobs no longer referenced are deleted.
+    pub fn prune(&self, max_size: u64) -> io::Result<Vec<CacheEntry>> {
+        self.evict(max_size, None)
+    }
+
+    /// Prune, but never evict the entry for `keep` so a fresh download stays usable
+    fn evict(&self, max_size: u64, keep: Option<&str>) -> io::Result<Vec<CacheEntry>> {
+        let (mut entries, kept): (Vec<_>, Vec<_>) = self
+            .entries()?
+            .into_iter()
+            .partition(|entry| Some(entry.url.as_str()) != keep);
+        let mut removed = Vec::new();
+        while blobs_size(&[entries.as_slice(), kept.as_slice()].concat()) > max_size {
+            let Some(entry) = entries.pop() else { break };
+            remove_file(&self.index_path(&entry.url))?;
+            removed.push(entry);
+        }
+        entries.extend(kept);
+        self.collect_garbage(&entries)?;
+        Ok(removed)
+    }
+
+    /// Check every blob against its SHA-256, removing corrupt entries
+    ///
+    /// Returns the entries that were removed.
+    pub fn verify(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = self.entries()?;
+        let mut corrupt = Vec::new();
+        let mut checked = HashSet::new();
+        for entry in &entries {
+            if checked.contains(&entry.sha256) {
+                continue;
+            }
+            let blob = self.blob_path(&entry.sha256);
+            if sha256_file(&blob).is_ok_and(|actual| actual == entry.sha256) {
+                checked.insert(entry.sha256.clone());
+            } else {
+                remove_file(&blob)?;
+            }
+        }
+        entries.retain(|entry| {
+            let ok = checked.contains(&entry.sha256);
+            if !ok {
+                corrupt.push(entry.clone());
+            }
+            ok
+        });
+        for entry in &corrupt {
+            remove_file(&self.index_path(&entry.url))?;
+        }
+        self.collect_garbage(&entries)?;
+        Ok(corrupt)
+    }
+
+    /// Delete blobs that no entry refers to, and temporary files older than an hour
+    fn collect_garbage(&self, entries: &[CacheEntry]) -> io::Result<()> {
+        let referenced: HashSet<&str> = entries.iter().map(|e| e.sha256.as_str()).collect();
+        let dir = match std::fs::read_dir(self.dir.join("blobs")) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for file in dir {
+            let file = file?;
+            let name = file.file_name().to_string_lossy().into_owned();
+            // Temporary files may belong to a download still in progress
+            let stale = !name.starts_with("tmp-")
+                || file
+                    .metadata()?
+                    .modified()?
+                    .elapsed()
+                    .is_ok_and(|age| age.as_secs() > 60 * 60);
+            if stale && !referenced.contains(name.as_str()) {
+                remove_file(&file.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a body to its blob, returning its SHA-256 and size
+    ///
+    /// Stops reading past `max_size` bytes and returns None for such bodies.
+    fn store(
+        &self,
+        reader: impl io::Read,
+        max_size: Option<u64>,
+    ) -> io::Result<Option<(String, u64)>> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let blobs = self.dir.join("blobs");
+        std::fs::create_dir_all(&blobs)?;
+        // Concurrent downloads each write their own temporary file
+        let temp = blobs.join(format!(
+            "tmp-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let limit = max_size.map_or(u64::MAX, |max| max + 1);
+        let size = io::copy(&mut reader.take(limit), &mut std::fs::File::create(&temp)?);
+        let result = size.and_then(|size| {
+            if max_size.is_some_and(|max| size > max) {
+                return Ok(None);
+            }
+            Ok(Some((sha256_file(&temp)?, size)))
+        });
+        match result {
+            Ok(Some((sha256, size))) => {
+                std::fs::rename(&temp, self.blob_path(&sha256))?;
+                Ok(Some((sha256, size)))
+            }
+            Ok(None) => {
+                let _ = std::fs::remove_file(&temp);
+                Ok(None)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&temp);
+                Err(e)
+            }
+        }
+    }
+
+    /// Mark an entry as used now, for eviction order
+    fn touch(&self, index: &Path, entry: &CacheEntry) -> io::Result<()> {
+        write_entry(
+            index,
+            &CacheEntry {
+                used: now(),
+                ..entry.clone()
+            },
+        )
+    }
+
+    fn index_path(&self, url: &str) -> PathBuf {
+        let key: String = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.dir.join("index").join(key)
+    }
+
+    fn blob_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("blobs").join(sha256)
+    }
+}
+
+/// Size of the distinct blobs referenced by `entries`
+fn blobs_size(entries: &[CacheEntry]) -> u64 {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter(|entry| seen.insert(&entry.sha256))
+        .map(|entry| entry.size)
+        .sum()
+}
+
+/// Parse an index file of tab separated
+/// `<url> <sha256> <etag|-> <immutable> <used> <size> <fresh until|->`, followed
+/// by a `<name>: <value>` field per stored header
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let fields: Vec<&str> = text.trim_end().split('\t').collect();
+    let [url, sha256, etag, immutable, used, size, ref rest @ ..] = fields[..] else {
+        return None;
+    };
+    // Entries written by older versions end after the size
+    let (fresh_until, headers) = match rest {
+        [fresh_until, headers @ ..] => (fresh_until.parse().ok(), headers),
+        [] => (None, rest),
+    };
+    Some(CacheEntry {
+        url: url.to_string(),
+        sha256: sha256.to_string(),
+        etag: (etag != "-").then(|| etag.to_string()),
+        immutable: immutable == "1",
+        fresh_until,
+        used: used.parse().ok()?,
+        size: size.parse().ok()?,
+        headers: headers
+            .iter()
+            .filter_map(|header| header.split_once(": "))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    })
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut line = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        entry.url,
+        entry.sha256,
+        entry.etag.as_deref().unwrap_or("-"),
+        u8::from(entry.immutable),
+        entry.used,
+        entry.size,
+        entry
+            .fresh_until
+            .map_or("-".to_string(), |fresh| fresh.to_string())
+    );
+    for (name, value) in &entry.headers {
+        line.push_str(&format!("\t{}: {}", name, value));
+    }
+    line.push('\n');
+    std::fs::write(path, line)
+}
+
+/// When a fresh copy of `resource` needs revalidation, None if it never or always does
+fn fresh_until(resource: &Resource) -> Option<u64> {
+    let moving_release = matches!(resource, Resource::Release { .. }) && !resource.is_immutable();
+    moving_release.then(|| now() + MOVING_RELEASE_TTL)
+}
+
+/// Remove a file, ignoring files that are already gone
+fn remove_file(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,112 @@
+use crate::rewrite::{Change, retarget_clone_url, retarget_url};
+use crate::{Proxy, Resource};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+// `git = "<url>"` in Cargo.toml dependency tables
+fn manifest_git_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(?P<key>(?-u:\b)git[ \t]*=[ \t]*")(?P<url>[^"]+)""#).unwrap())
+}
+
+// `source = "git+<url>?<query>#<rev>"` in Cargo.lock
+fn lock_source_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"(?P<key>(?-u:\b)source[ \t]*=[ \t]*"git\+)(?P<url>[^"?#]+)(?P<rest>[^"]*)""#)
+            .unwrap()
+    })
+}
+
+/// Rewrite GitHub git dependency URLs in a Cargo.toml or Cargo.lock
+///
+/// Both files should be rewritten with the same proxy so the lock file keeps
+/// matching the manifest. Passing `Proxy::Github` restores canonical URLs.
+pub fn rewrite_cargo(text: &str, proxy: &Proxy) -> (String, Vec<Change>) {
+    let mut changes = Vec::new();
+    let mut out = String::with_capacity(text.len());
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let mut replace = |caps: &Captures| {
+            let url = &caps["url"];
+            let rest = caps.name("rest").map_or("", |m| m.as_str());
+            match retarget_clone_url(url, proxy).or_else(|| retarget_url(url, proxy)) {
+                Some(new) => {
+                    changes.push(Change {
+                        line: i + 1,
+                        from: url.to_string(),
+                        to: new.clone(),
+                    });
+                    format!("{}{}{}\"", &caps["key"], new, rest)
+                }
+                None => caps[0].to_string(),
+            }
+        };
+        let line = manifest_git_regex().replace_all(line, &mut replace);
+        let line = lock_source_regex().replace_all(&line, &mut replace);
+        out.push_str(&line);
+    }
+
+    (out, changes)
+}
+
+/// Generate `.cargo/config.toml` source replacement for the git sources in a Cargo.lock
+///
+/// Each GitHub git source is replaced by the same repository cloned through
+/// `proxy`, leaving Cargo.toml and Cargo.lock untouched. Returns None if the
+/// proxy can't be used with git.
+pub fn cargo_source_config(lock: &str, proxy: &Proxy) -> Option<String> {
+    proxy.clone_prefix()?;
+    let mut out = String::new();
+    let mut seen = Vec::new();
+    let mut names = Vec::new();
+
+    for caps in lock_source_regex().captures_iter(lock) {
+        let url = &caps["url"];
+        // Sources are identified without the locked revision after `#`
+        let query = caps["rest"].split('#').next().unwrap_or_default();
+        let source = format!("git+{}{}", url, query);
+        if seen.contains(&source) {
+            continue;
+        }
+
+        let Ok((current, Resource::Repository { owner, repo })) = Resource::from_proxied_url(url)
+        else {
+            continue;
+        };
+        if current == *proxy {
+            continue;
+        }
+        let Some(to) = retarget_clone_url(url, proxy) else {
+            continue;
+        };
+
+        // branch = "main", tag = "v1" or rev = "abc" from the source query
+        let selector = query
+            .strip_prefix('?')
+            .and_then(|query| query.split_once('='))
+            .map(|(key, value)| format!("{} = \"{}\"\n", key, value))
+            .unwrap_or_default();
+        // The same repository may be locked at several branches or tags
+        let base = format!("{}-{}-{}", proxy, owner, repo);
+        let mut name = base.clone();
+        let mut n = 2;
+        while names.contains(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        out.push_str(&format!(
+            "[source.\"{}\"]\ngit = \"{}\"\n{}replace-with = \"{}\"\n\n",
+            source, url, selector, name
+        ));
+        out.push_str(&format!(
+            "[source.\"{}\"]\ngit = \"{}\"\n{}\n",
+            name, to, selector
+        ));
+        seen.push(source);
+        names.push(name);
+    }
+
+    Some(out)
+}
@@ -0,0 +1,180 @@
+use crate::download::{agent, check_downloadable, describe};
+use crate::{ConversionError, Proxy, ProxyChain, Resource};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+
+/// Lowercase hex SHA-256 of everything read from `reader`
+pub fn sha256_reader(mut reader: impl io::Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Lowercase hex SHA-256 of a file
+pub fn sha256_file(path: impl AsRef<Path>) -> io::Result<String> {
+    sha256_reader(std::fs::File::open(path)?)
+}
+
+/// Check that the file at `path` has the expected SHA-256
+pub fn verify_sha256(path: impl AsRef<Path>, expected: &str) -> Result<(), ConversionError> {
+    let actual = sha256_file(path)?;
+    let expected = expected.trim().to_ascii_lowercase();
+    if actual != expected {
+        return Err(ConversionError::ChecksumMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Find the published SHA-256 of a release asset
+///
+/// Looks for sibling checksum assets in the same release (`<name>.sha256`,
+/// `<name>.sha256sum`, `SHA256SUMS`, `sha256sums.txt`, `checksums.txt`),
+/// fetched through `proxy` like the asset itself. Returns None for other
+/// resource kinds or when no checksum for the asset is published.
+pub fn discover_sha256(resource: &Resource, proxy: &Proxy, chain: &ProxyChain) -> Option<String> {
+    let Resource::Release {
+        owner,
+        repo,
+        tag,
+        name,
+    } = resource
+    else {
+        return None;
+    };
+    let agent = agent(chain);
+
+    let candidates = [
+        format!("{}.sha256", name),
+        format!("{}.sha256sum", name),
+        "SHA256SUMS".to_string(),
+        "sha256sums.txt".to_string(),
+        "checksums.txt".to_string(),
+    ];
+    candidates.into_iter().find_map(|candidate| {
+        let sibling = Resource::release(owner.clone(), repo.clone(), tag.clone(), candidate);
+        let url = chain.url(proxy, &sibling)?;
+        let text = agent.get(&url).call().ok()?.into_string().ok()?;
+        parse_checksums(&text, name)
+    })
+}
+
+/// Find the checksum of `name` in the contents of a checksum file
+///
+/// Supports `<hex>  <name>`, `<hex> *<name>`, BSD style
+/// `SHA256 (<name>) = <hex>` and a lone `<hex>` for single file checksums.
+pub(crate) fn parse_checksums(text: &str, name: &str) -> Option<String> {
+    let is_sha256 = |s: &str| s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit());
+    let matches = |file: &str| {
+        let file = file.trim().trim_start_matches('*');
+        file == name || file.rsplit('/').next() == Some(name)
+    };
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+
+    for line in &lines {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("SHA256 (")
+            && let Some((file, hash)) = rest.split_once(") = ")
+            && matches(file)
+            && is_sha256(hash.trim())
+        {
+            return Some(hash.trim().to_ascii_lowercase());
+        }
+        if let Some((hash, file)) = line.split_once(char::is_whitespace)
+            && is_sha256(hash)
+            && matches(file)
+        {
+            return Some(hash.to_ascii_lowercase());
+        }
+    }
+
+    // `<name>.sha256` files often hold nothing but the hash
+    match lines[..] {
+        [line] if is_sha256(line.trim()) => Some(line.trim().to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// Digests of the same resource as served by several proxies
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Audit {
+    /// Proxy whose content the others were compared against
+    pub reference: Proxy,
+    /// SHA-256 served by each reachable proxy, in chain order
+    pub digests: Vec<(Proxy, String)>,
+    /// Proxies that couldn't be reached, with the reason
+    pub failures: Vec<(Proxy, String)>,
+}
+
+/// Hash a resource through every proxy of the chain and compare the contents
+///
+/// Bodies are streamed into the hasher, nothing is written to disk. The
+/// reference is `Proxy::Github` when it is in the chain and reachable,
+/// otherwise the digest served by most proxies. Fails with
+/// `ContentMismatch` naming the first proxy that disagrees, or with
+/// `DownloadFailed` when fewer than two proxies could be compared.
+pub fn audit(resource: &Resource, chain: &ProxyChain) -> Result<Audit, ConversionError> {
+    check_downloadable(resource)?;
+    let agent = agent(chain);
+    let (tx, rx) = mpsc::channel();
+    let urls = chain.urls(resource);
+
+    for (i, (proxy, url)) in urls.iter().cloned().enumerate() {
+        let agent = agent.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let digest = agent
+                .get(&url)
+                .call()
+                .map_err(describe)
+                .and_then(|r| sha256_reader(r.into_reader()).map_err(|e| e.to_string()));
+            let _ = tx.send((i, proxy, url, digest));
+        });
+    }
+    drop(tx);
+
+    let mut results: Vec<_> = rx.into_iter().collect();
+    results.sort_by_key(|(i, ..)| *i);
+
+    let mut digests = Vec::new();
+    let mut failures = Vec::new();
+    for (_, proxy, url, digest) in results {
+        match digest {
+            Ok(digest) => digests.push((proxy, digest)),
+            Err(e) => failures.push((proxy, format!("{}: {}", url, e))),
+        }
+    }
+    if digests.len() < 2 {
+        return Err(ConversionError::DownloadFailed(failures));
+    }
+
+    let count = |digest: &str| digests.iter().filter(|(_, d)| d == digest).count();
+    let (reference, expected) = digests
+        .iter()
+        .find(|(proxy, _)| *proxy == Proxy::Github)
+        .or_else(|| {
+            // Earliest proxy wins ties
+            digests.iter().rev().max_by_key(|(_, digest)| count(digest))
+        })
+        .cloned()
+        .unwrap_or_else(|| digests[0].clone());
+
+    if let Some((proxy, actual)) = digests.iter().find(|(_, d)| *d != expected) {
+        return Err(ConversionError::ContentMismatch {
+            proxy: *proxy,
+            expected,
+            actual: actual.clone(),
+        });
+    }
+
+    Ok(Audit {
+        reference,
+        digests,
+        failures,
+    })
+}
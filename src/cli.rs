@@ -1,16 +1,47 @@
+use crate::cargo;
+use crate::dockerfile;
 use crate::error::ConversionError;
+use crate::gitconfig;
 use crate::proxy::Proxy;
 use crate::resource::Resource;
+use crate::rewrite::{self, Change};
+use crate::workflow;
+use std::io::Read as _;
 use std::str::FromStr as _;
 
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
 
+    // Commands that work without arguments
+    match args.get(1).map(|a| a.as_str()) {
+        #[cfg(feature = "download")]
+        Some("probe") => return run_probe(&args[2..]),
+        #[cfg(feature = "server")]
+        Some("serve") => return run_serve(&args[2..]),
+        _ => {}
+    }
+
     if args.len() < 3 {
         print_usage();
         std::process::exit(1);
     }
 
+    match args[1].as_str() {
+        "rewrite" => return run_rewrite(&args[2..]),
+        "dockerfile" => return run_dockerfile(&args[2..]),
+        "workflow" => return run_workflow(&args[2..]),
+        "gitconfig" => return run_gitconfig(&args[2..]),
+        "registry" => return run_registry(&args[2..]),
+        "cargo" => return run_cargo(&args[2..]),
+        #[cfg(feature = "download")]
+        "download" => return run_download(&args[2..]),
+        #[cfg(feature = "download")]
+        "audit" => return run_audit(&args[2..]),
+        #[cfg(feature = "download")]
+        "cache" => return run_cache(&args[2..]),
+        _ => {}
+    }
+
     let proxy_type_str = &args[1];
     let resource_type_str = &args[2];
 
@@ -47,32 +78,652 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                 args[6].clone(),
             )
         }
+        "tree" => {
+            if args.len() != 6 && args.len() != 7 {
+                return Err(ConversionError::InvalidArguments(
+                    "tree requires 3 or 4 arguments: owner repo reference [path]".to_string(),
+                )
+                .into());
+            }
+            Resource::tree(
+                args[3].clone(),
+                args[4].clone(),
+                args[5].clone(),
+                args.get(6).cloned().unwrap_or_default(),
+            )
+        }
+        "image" => {
+            if args.len() != 4 {
+                return Err(ConversionError::InvalidArguments(
+                    "image requires 1 argument: reference".to_string(),
+                )
+                .into());
+            }
+            match Resource::try_from(args[3].as_str())? {
+                image @ Resource::Image { .. } => image,
+                _ => return Err(ConversionError::InvalidUrl(args[3].clone()).into()),
+            }
+        }
         _ => {
             return Err(ConversionError::InvalidResourceType(resource_type_str.clone()).into());
         }
     };
 
-    // Generate and print URL
-    match resource.url(&proxy_type) {
-        Some(url) => {
-            println!("{}", url);
-            Ok(())
+    // Generate and print URL, or the pull reference of an image
+    let output =
+        match resource {
+            Resource::Image { .. } => resource.pull_reference(&proxy_type).ok_or(
+                ConversionError::UnsupportedResource {
+                    proxy: proxy_type,
+                    kind: resource.kind(),
+                },
+            )?,
+            _ => resource.try_url(&proxy_type)?,
+        };
+    println!("{}", output);
+    Ok(())
+}
+
+/// github-proxy rewrite <proxy-type> [--dry-run] [files...]
+fn run_rewrite(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy_type = Proxy::from_str(&args[0])?;
+    let dry_run = args[1..].iter().any(|a| a == "--dry-run");
+    let files: Vec<&String> = args[1..].iter().filter(|a| *a != "--dry-run").collect();
+
+    // Without files act as a filter from stdin to stdout
+    if files.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        print!("{}", rewrite::rewrite_text(&text, &proxy_type));
+        return Ok(());
+    }
+
+    for file in files {
+        let text = std::fs::read_to_string(file)?;
+        let new = rewrite::rewrite_text(&text, &proxy_type);
+        if dry_run {
+            print!("{}", rewrite::rewrite_diff(file, &text, &new));
+        } else if new != text {
+            std::fs::write(file, new)?;
+        }
+    }
+    Ok(())
+}
+
+/// github-proxy dockerfile <proxy-type> [--dry-run] [files...]
+fn run_dockerfile(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy_type = Proxy::from_str(&args[0])?;
+    let dry_run = args[1..].iter().any(|a| a == "--dry-run");
+    let mut files: Vec<String> = args[1..]
+        .iter()
+        .filter(|a| *a != "--dry-run")
+        .cloned()
+        .collect();
+    if files.is_empty() {
+        files.push("Dockerfile".to_string());
+    }
+
+    rewrite_files(&files, dry_run, |text| {
+        dockerfile::rewrite_dockerfile(text, &proxy_type)
+    })
+}
+
+/// github-proxy workflow <proxy-type> [--dry-run] [dir]
+fn run_workflow(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy_type = Proxy::from_str(&args[0])?;
+    let dry_run = args[1..].iter().any(|a| a == "--dry-run");
+    let dir = args[1..]
+        .iter()
+        .find(|a| *a != "--dry-run")
+        .map_or(".", |a| a.as_str());
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(std::path::Path::new(dir).join(".github/workflows"))? {
+        let path = entry?.path();
+        if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yml" | "yaml")
+        ) {
+            files.push(path.to_string_lossy().into_owned());
+        }
+    }
+    files.sort();
+
+    rewrite_files(&files, dry_run, |text| {
+        workflow::rewrite_workflow(text, &proxy_type)
+    })
+}
+
+/// github-proxy gitconfig <proxy-type|--remove> [--file <path>]
+fn run_gitconfig(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let file = option(args, "--file")?;
+    let remove = args[0] == "--remove";
+    let proxy_type = if remove {
+        None
+    } else {
+        Some(Proxy::from_str(&args[0])?)
+    };
+    let unsupported = |proxy: &Proxy| {
+        ConversionError::InvalidArguments(format!("{} proxy does not support git clone", proxy))
+    };
+
+    let Some(file) = file else {
+        // Without a file just print the snippet
+        let Some(proxy) = proxy_type else {
+            return Err(ConversionError::InvalidArguments(
+                "--remove requires --file <path>".to_string(),
+            )
+            .into());
+        };
+        let snippet = gitconfig::gitconfig_snippet(&proxy).ok_or_else(|| unsupported(&proxy))?;
+        print!("{}", snippet);
+        return Ok(());
+    };
+
+    let text = match std::fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let new = match proxy_type {
+        Some(proxy) => {
+            gitconfig::apply_gitconfig(&text, &proxy).ok_or_else(|| unsupported(&proxy))?
+        }
+        None => gitconfig::remove_gitconfig(&text),
+    };
+    if new != text {
+        std::fs::write(file, new)?;
+    }
+    Ok(())
+}
+
+/// github-proxy registry <proxy-type|mirror-url> [--registry <host>] [--daemon-json]
+fn run_registry(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let registry = option(args, "--registry")?.map_or("ghcr.io", |r| r.as_str());
+    // Anything that isn't a proxy type is the URL of a registry mirror
+    let mirror = match Proxy::from_str(&args[0]) {
+        Ok(proxy) => crate::registry_mirror(&proxy, registry).ok_or_else(|| {
+            ConversionError::InvalidArguments(format!(
+                "{} proxy does not mirror {}",
+                proxy, registry
+            ))
+        })?,
+        Err(_) => args[0].clone(),
+    };
+
+    if args.iter().any(|a| a == "--daemon-json") {
+        let snippet = crate::daemon_json_snippet(&mirror).ok_or_else(|| {
+            ConversionError::InvalidArguments(format!(
+                "Docker registry mirrors can't have a path: {}",
+                mirror
+            ))
+        })?;
+        print!("{}", snippet);
+    } else {
+        print!("{}", crate::containerd_hosts_snippet(registry, &mirror));
+    }
+    Ok(())
+}
+
+/// github-proxy cargo <proxy-type> [--dry-run] [--config] [dir]
+fn run_cargo(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy_type = Proxy::from_str(&args[0])?;
+    let dry_run = args[1..].iter().any(|a| a == "--dry-run");
+    let config = args[1..].iter().any(|a| a == "--config");
+    let dir = std::path::Path::new(
+        args[1..]
+            .iter()
+            .find(|a| !a.starts_with("--"))
+            .map_or(".", |a| a.as_str()),
+    );
+    let lock = dir.join("Cargo.lock");
+
+    if config {
+        let text = std::fs::read_to_string(&lock)?;
+        let config = cargo::cargo_source_config(&text, &proxy_type).ok_or_else(|| {
+            ConversionError::InvalidArguments(format!(
+                "{} proxy does not support git clone",
+                proxy_type
+            ))
+        })?;
+        print!("{}", config);
+        return Ok(());
+    }
+
+    let mut files = vec![dir.join("Cargo.toml").to_string_lossy().into_owned()];
+    if lock.exists() {
+        files.push(lock.to_string_lossy().into_owned());
+    }
+    rewrite_files(&files, dry_run, |text| {
+        cargo::rewrite_cargo(text, &proxy_type)
+    })
+}
+
+/// github-proxy download <url> [-o <path>] [--proxy <list>] [--race | --cache] [--sha256 <hex> | --verify] [--timeout <seconds>]
+#[cfg(feature = "download")]
+fn run_download(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, resource) = Resource::from_proxied_url(&args[0])?;
+    let mut ranking = crate::Ranking::load();
+    // Without an explicit list, try proxies in the order of past results
+    let chain = match option(args, "--proxy")? {
+        Some(list) => crate::ProxyChain::from_str(list)?,
+        None => {
+            let all: Vec<Proxy> = <Proxy as strum::IntoEnumIterator>::iter().collect();
+            crate::ProxyChain::new(ranking.rank(&resource, &all))
+        }
+    }
+    .with_timeout(timeout(args)?);
+    let path = match option(args, "-o")? {
+        Some(path) => path.clone(),
+        None => file_name(&args[0]),
+    };
+
+    let race = args.iter().any(|a| a == "--race");
+    let cache =
+        if args.iter().any(|a| a == "--cache") {
+            Some(crate::Cache::open_default().ok_or_else(|| {
+                ConversionError::InvalidArguments("no cache directory".to_string())
+            })?)
+        } else {
+            None
+        };
+    let result = if let Some(cache) = &cache {
+        cache.fetch(&resource, &chain).and_then(|cached| {
+            std::fs::copy(&cached.path, &path)?;
+            Ok(cached.proxy)
+        })
+    } else if race {
+        crate::race(&resource, &chain, &path).map(Some)
+    } else {
+        crate::fetch(&resource, &chain, &path).map(Some)
+    };
+
+    match &result {
+        Ok(None) => {}
+        Ok(Some(proxy)) => {
+            // Serial fetching only reaches a proxy after the previous ones failed
+            if !race {
+                // Proxies that can't render the resource were never asked
+                for failed in chain
+                    .proxies()
+                    .iter()
+                    .take_while(|p| *p != proxy)
+                    .filter(|p| resource.url(p).is_some())
+                {
+                    ranking.record_failure(*failed);
+                }
+            }
+            ranking.record_success(*proxy, None);
+        }
+        Err(ConversionError::DownloadFailed(attempts)) => {
+            for (proxy, _) in attempts {
+                ranking.record_failure(*proxy);
+            }
+        }
+        Err(_) => {}
+    }
+    let _ = ranking.save();
+
+    let proxy = match result? {
+        Some(proxy) => {
+            eprintln!("Downloaded {} via {}", path, proxy);
+            proxy
         }
         None => {
+            eprintln!("Copied {} from the cache", path);
+            // Look for checksums through the first proxy that serves the resource
+            chain
+                .urls(&resource)
+                .first()
+                .map_or(Proxy::Github, |(proxy, _)| *proxy)
+        }
+    };
+
+    let expected = option(args, "--sha256")?.cloned();
+    let discover = args.iter().any(|a| a == "--verify");
+    let checked = verify(
+        &path,
+        &resource,
+        &proxy,
+        &chain,
+        expected,
+        discover,
+        cache.as_ref(),
+    );
+    if let Some(expected) = checked? {
+        eprintln!("Verified SHA-256 {}", expected);
+    }
+    Ok(())
+}
+
+/// Check the SHA-256 of a downloaded file, the published one if `discover` is set
+///
+/// The file is deleted when the check fails or no checksum is published,
+/// so unverified content is never left behind. A file copied from `cache`
+/// is evicted from it too, or the cache would serve it again.
+#[cfg(feature = "download")]
+pub(crate) fn verify(
+    path: &str,
+    resource: &Resource,
+    proxy: &Proxy,
+    chain: &crate::ProxyChain,
+    expected: Option<String>,
+    discover: bool,
+    cache: Option<&crate::Cache>,
+) -> Result<Option<String>, ConversionError> {
+    let expected = match expected {
+        Some(hex) => Some(hex),
+        None if discover => crate::discover_sha256(resource, proxy, chain),
+        None => return Ok(None),
+    };
+    let result = match expected {
+        Some(expected) => crate::verify_sha256(path, &expected).map(|_| expected),
+        None => Err(ConversionError::ChecksumNotFound(path.to_string())),
+    };
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+        if let Some(cache) = cache {
+            cache.remove(resource)?;
+        }
+    }
+    result.map(Some)
+}
+
+/// github-proxy probe [url] [--proxy <list>] [--save <path>] [--timeout <seconds>]
+#[cfg(feature = "download")]
+fn run_probe(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let url = match args.first() {
+        Some(url) if !url.starts_with("--") => url.as_str(),
+        _ => "https://github.com/easy-install/easy-install/raw/main/install.sh",
+    };
+    let (_, resource) = Resource::from_proxied_url(url)?;
+    crate::download::check_downloadable(&resource)?;
+    let chain = match option(args, "--proxy")? {
+        Some(list) => crate::ProxyChain::from_str(list)?,
+        None => crate::ProxyChain::default(),
+    }
+    .with_timeout(timeout(args)?);
+
+    let probes = crate::probe(&resource, &chain);
+
+    let mut cache = crate::Ranking::load();
+    // Proxies that don't support the resource were never contacted
+    for probe in probes.iter().filter(|p| resource.url(&p.proxy).is_some()) {
+        if probe.is_ok() {
+            cache.record_success(probe.proxy, Some(probe.latency));
+        } else {
+            cache.record_failure(probe.proxy);
+        }
+    }
+    let _ = cache.save();
+
+    let ranking = format_ranking(&probes);
+    print!("{}", ranking);
+    if let Some(path) = option(args, "--save")? {
+        std::fs::write(path, ranking)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "download")]
+fn format_ranking(probes: &[crate::Probe]) -> String {
+    let mut out = String::new();
+    for (i, probe) in probes.iter().enumerate() {
+        let status = probe.status.map_or("-".to_string(), |s| s.to_string());
+        let throughput = probe
+            .throughput
+            .map_or("-".to_string(), |t| format!("{:.1} KiB/s", t / 1024.0));
+        out.push_str(&format!(
+            "{}. {:<11} {:>3} {:>6} ms {:>14}",
+            i + 1,
+            probe.proxy.to_string(),
+            status,
+            probe.latency.as_millis(),
+            throughput
+        ));
+        if let Some(error) = &probe.error {
+            out.push_str(&format!("  {}", error));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// github-proxy audit <url> [--proxy <list>] [--timeout <seconds>]
+#[cfg(feature = "download")]
+fn run_audit(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, resource) = Resource::from_proxied_url(&args[0])?;
+    let chain = match option(args, "--proxy")? {
+        Some(list) => crate::ProxyChain::from_str(list)?,
+        None => crate::ProxyChain::default(),
+    }
+    .with_timeout(timeout(args)?);
+
+    let report = crate::audit(&resource, &chain)?;
+    for (proxy, digest) in &report.digests {
+        println!("{:<11} {}", proxy.to_string(), digest);
+    }
+    for (proxy, error) in &report.failures {
+        println!("{:<11} unreachable: {}", proxy.to_string(), error);
+    }
+    eprintln!("All reachable proxies match {}", report.reference);
+    Ok(())
+}
+
+/// github-proxy cache <list|prune|verify> [--max-size <bytes>]
+#[cfg(feature = "download")]
+fn run_cache(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = crate::Cache::open_default()
+        .ok_or_else(|| ConversionError::InvalidArguments("no cache directory".to_string()))?;
+    let print = |entry: &crate::CacheEntry| {
+        let kind = if entry.immutable {
+            "immutable"
+        } else if entry.fresh_until.is_some() {
+            "expires"
+        } else {
+            "revalidate"
+        };
+        println!("{:>12} {:<10} {}", entry.size, kind, entry.url);
+    };
+
+    match args[0].as_str() {
+        "list" => {
+            let entries = cache.entries()?;
+            entries.iter().for_each(print);
+            let total: u64 = entries.iter().map(|e| e.size).sum();
             eprintln!(
-                "Error: {} proxy does not support {:?} resources",
-                proxy_type, resource
-            );
-            eprintln!(
-                "Note: jsdelivr and statically do not support release assets from /releases/download/"
+                "{} entries, {} bytes in {}",
+                entries.len(),
+                total,
+                cache.dir().display()
             );
-            std::process::exit(1);
         }
+        "prune" => {
+            let max_size = match option(args, "--max-size")? {
+                Some(size) => size.parse().map_err(|_| {
+                    ConversionError::InvalidArguments(format!("invalid --max-size: {}", size))
+                })?,
+                None => 0,
+            };
+            let removed = cache.prune(max_size)?;
+            removed.iter().for_each(print);
+            eprintln!("Removed {} entries", removed.len());
+        }
+        "verify" => {
+            let corrupt = cache.verify()?;
+            corrupt.iter().for_each(print);
+            if !corrupt.is_empty() {
+                return Err(ConversionError::InvalidArguments(format!(
+                    "removed {} corrupt entries",
+                    corrupt.len()
+                ))
+                .into());
+            }
+            eprintln!("All entries match their SHA-256");
+        }
+        other => {
+            return Err(ConversionError::InvalidArguments(format!(
+                "unknown cache command: {}",
+                other
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// github-proxy serve [--proxy <proxy-type>] [--listen <addr>] [--stream] [--upstream <url>] [--max-size <bytes>] [--cache [--cache-size <bytes>]]
+#[cfg(feature = "server")]
+fn run_serve(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let proxy = match option(args, "--proxy")? {
+        Some(proxy) => Proxy::from_str(proxy)?,
+        None => Proxy::default(),
+    };
+    let listen = option(args, "--listen")?.map_or("127.0.0.1:8080", |a| a.as_str());
+
+    let mut config = crate::ServerConfig::new(proxy);
+    config.stream = args.iter().any(|a| a == "--stream");
+    config.chain = config.chain.with_timeout(timeout(args)?);
+    if let Some(upstream) = option(args, "--upstream")? {
+        config.chain = config.chain.with_origin(proxy, upstream.as_str());
+    }
+    if let Some(max_size) = option(args, "--max-size")? {
+        config.max_size = Some(max_size.parse().map_err(|_| {
+            ConversionError::InvalidArguments(format!("invalid --max-size: {}", max_size))
+        })?);
+    }
+    #[cfg(feature = "download")]
+    if args.iter().any(|a| a == "--cache") {
+        let mut cache = crate::Cache::open_default()
+            .ok_or_else(|| ConversionError::InvalidArguments("no cache directory".to_string()))?;
+        if let Some(size) = option(args, "--cache-size")? {
+            cache = cache.with_max_size(size.parse().map_err(|_| {
+                ConversionError::InvalidArguments(format!("invalid --cache-size: {}", size))
+            })?);
+        }
+        config.cache = Some(cache);
+    }
+
+    let list = |name: &str| -> Result<Vec<String>, ConversionError> {
+        Ok(option(args, name)?.map_or_else(Vec::new, |list| {
+            list.split(',')
+                .map(|item| item.trim().to_string())
+                .collect()
+        }))
+    };
+    config.policy.allow_owners = list("--allow-owners")?;
+    config.policy.deny_owners = list("--deny-owners")?;
+    config.policy.allow_repos = list("--allow-repos")?;
+    config.policy.deny_repos = list("--deny-repos")?;
+    config.policy.allow_kinds = list("--allow-kinds")?;
+    if let Some(kind) = config
+        .policy
+        .allow_kinds
+        .iter()
+        .find(|kind| !Resource::KINDS.contains(&kind.as_str()))
+    {
+        return Err(ConversionError::InvalidResourceType(kind.clone()).into());
+    }
+    if let Some(rate) = option(args, "--rate-limit")? {
+        config.policy.rate_limit = Some(crate::RateLimit {
+            requests: rate.parse().map_err(|_| {
+                ConversionError::InvalidArguments(format!("invalid --rate-limit: {}", rate))
+            })?,
+            window: std::time::Duration::from_secs(60),
+        });
+    }
+
+    if config.stream {
+        eprintln!("Streaming http://{} from {}", listen, proxy);
+    } else {
+        eprintln!("Redirecting http://{} to {}", listen, proxy);
+    }
+    crate::serve(listen, config)?;
+    Ok(())
+}
+
+/// Last path segment of a URL, used as the default output file name
+#[cfg(feature = "download")]
+fn file_name(url: &str) -> String {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(url)
+        .to_string()
+}
+
+/// Stall timeout from `--timeout <seconds>`, 30 seconds by default
+#[cfg(any(feature = "download", feature = "server"))]
+fn timeout(args: &[String]) -> Result<std::time::Duration, ConversionError> {
+    let seconds = match option(args, "--timeout")? {
+        Some(seconds) => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .ok_or_else(|| {
+                ConversionError::InvalidArguments(format!("invalid --timeout: {}", seconds))
+            })?,
+        None => 30,
+    };
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
+/// Value following `name` in the arguments, if the option is present
+fn option<'a>(args: &'a [String], name: &str) -> Result<Option<&'a String>, ConversionError> {
+    match args.iter().position(|a| a == name) {
+        Some(i) => args
+            .get(i + 1)
+            .map(Some)
+            .ok_or_else(|| ConversionError::InvalidArguments(format!("{} requires a value", name))),
+        None => Ok(None),
+    }
+}
+
+/// Apply a rewriter to each file, printing a diff for dry runs or the changes otherwise
+fn rewrite_files<F>(files: &[String], dry_run: bool, f: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> (String, Vec<Change>),
+{
+    for file in files {
+        let text = std::fs::read_to_string(file)?;
+        let (new, changes) = f(&text);
+        if dry_run {
+            print!("{}", rewrite::rewrite_diff(file, &text, &new));
+        } else if !changes.is_empty() {
+            std::fs::write(file, new)?;
+            print_changes(file, &changes);
+        }
+    }
+    Ok(())
+}
+
+fn print_changes(file: &str, changes: &[Change]) {
+    for change in changes {
+        println!("{}:{}: {} -> {}", file, change.line, change.from, change.to);
     }
 }
 
 fn print_usage() {
     eprintln!("Usage: github-proxy <proxy-type> <resource-type> <args...>");
+    eprintln!("       github-proxy rewrite <proxy-type> [--dry-run] [files...]");
+    eprintln!("       github-proxy dockerfile <proxy-type> [--dry-run] [files...]");
+    eprintln!("       github-proxy workflow <proxy-type> [--dry-run] [dir]");
+    eprintln!("       github-proxy gitconfig <proxy-type|--remove> [--file <path>]");
+    eprintln!("       github-proxy cargo <proxy-type> [--dry-run] [--config] [dir]");
+    eprintln!(
+        "       github-proxy registry <proxy-type|mirror-url> [--registry <host>] [--daemon-json]"
+    );
+    eprintln!(
+        "       github-proxy download <url> [-o <path>] [--proxy <list>] [--race | --cache] [--sha256 <hex> | --verify] [--timeout <seconds>]"
+    );
+    eprintln!(
+        "       github-proxy probe [url] [--proxy <list>] [--save <path>] [--timeout <seconds>]"
+    );
+    eprintln!("       github-proxy audit <url> [--proxy <list>] [--timeout <seconds>]");
+    eprintln!("       github-proxy cache <list|prune|verify> [--max-size <bytes>]");
+    eprintln!(
+        "       github-proxy serve [--proxy <proxy-type>] [--listen <addr>] [--stream] [--upstream <url>] [--max-size <bytes>] [--cache [--cache-size <bytes>]] [--timeout <seconds>] [access options]"
+    );
     eprintln!();
     eprintln!("Proxy Types:");
     eprintln!("  github      Native Github (no proxy)");
@@ -88,6 +739,86 @@ fn print_usage() {
     eprintln!();
     eprintln!("  release <owner> <repo> <tag> <name>");
     eprintln!("    Generate URL for a release asset");
+    eprintln!("    jsdelivr and statically do not support release assets");
+    eprintln!();
+    eprintln!("  tree <owner> <repo> <reference> [path]");
+    eprintln!("    Generate URL listing a directory: the GitHub API contents endpoint,");
+    eprintln!("    or the jsdelivr directory listing");
+    eprintln!();
+    eprintln!("  image <reference>");
+    eprintln!("    Generate the reference to pull a ghcr.io image through the proxy,");
+    eprintln!("    e.g. ghcr.io/owner/image:tag (xget only)");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  rewrite <proxy-type> [--dry-run] [files...]");
+    eprintln!("    Rewrite GitHub URLs in files in place, or stdin to stdout");
+    eprintln!("    --dry-run prints a diff instead of writing");
+    eprintln!();
+    eprintln!("  dockerfile <proxy-type> [--dry-run] [files...]");
+    eprintln!("    Rewrite GitHub URLs in ADD, RUN and ARG instructions (default: Dockerfile)");
+    eprintln!("    Use proxy type github to restore canonical URLs");
+    eprintln!();
+    eprintln!("  workflow <proxy-type> [--dry-run] [dir]");
+    eprintln!("    Rewrite GitHub URLs in run scripts and with inputs of");
+    eprintln!("    <dir>/.github/workflows/*.yml (default dir: .)");
+    eprintln!();
+    eprintln!("  gitconfig <proxy-type|--remove> [--file <path>]");
+    eprintln!("    Print the url.<base>.insteadOf rule that routes github.com through the proxy,");
+    eprintln!("    or add/remove it in the given gitconfig file");
+    eprintln!();
+    eprintln!("  cargo <proxy-type> [--dry-run] [--config] [dir]");
+    eprintln!(
+        "    Rewrite git dependency URLs in <dir>/Cargo.toml and Cargo.lock (default dir: .)"
+    );
+    eprintln!("    --config prints .cargo/config.toml source replacement instead");
+    eprintln!();
+    eprintln!("  registry <proxy-type|mirror-url> [--registry <host>] [--daemon-json]");
+    eprintln!("    Print containerd hosts.toml pulling the registry (default: ghcr.io) through");
+    eprintln!("    the proxy or mirror, for /etc/containerd/certs.d/<host>/hosts.toml");
+    eprintln!("    --daemon-json prints a Docker daemon.json registry-mirrors entry instead;");
+    eprintln!("    Docker only uses it for Docker Hub and needs a mirror URL without a path");
+    eprintln!();
+    eprintln!(
+        "  download <url> [-o <path>] [--proxy <list>] [--race | --cache] [--sha256 <hex> | --verify] [--timeout <seconds>]"
+    );
+    eprintln!("    Download a GitHub URL, trying each proxy of a comma separated list in order");
+    eprintln!("    (default: all proxies)");
+    eprintln!("    --race requests all proxies at once and keeps the first to respond");
+    eprintln!("    --timeout moves on once a proxy stalls for that long (default: 30)");
+    eprintln!("    --cache keeps a copy in the local cache and reuses it on later downloads");
+    eprintln!("    --sha256 checks the file against a known hash, --verify against checksum");
+    eprintln!(
+        "    assets published in the same release (<name>.sha256, SHA256SUMS, checksums.txt)"
+    );
+    eprintln!();
+    eprintln!("  probe [url] [--proxy <list>] [--save <path>] [--timeout <seconds>]");
+    eprintln!("    Measure latency and throughput of each proxy for a resource and rank them");
+    eprintln!("    Results are remembered and used to order download attempts");
+    eprintln!();
+    eprintln!("  audit <url> [--proxy <list>] [--timeout <seconds>]");
+    eprintln!(
+        "    Hash a resource through several proxies and fail if any served different content"
+    );
+    eprintln!();
+    eprintln!("  cache <list|prune|verify> [--max-size <bytes>]");
+    eprintln!("    List cached downloads, evict least recently used ones down to --max-size");
+    eprintln!("    (default: everything), or remove entries whose content doesn't match its hash");
+    eprintln!();
+    eprintln!(
+        "  serve [--proxy <proxy-type>] [--listen <addr>] [--stream] [--upstream <url>] [--max-size <bytes>] [--cache [--cache-size <bytes>]] [--timeout <seconds>] [access options]"
+    );
+    eprintln!("    Redirect GitHub shaped paths such as /owner/repo/raw/main/file to the proxy");
+    eprintln!("    (default: github on 127.0.0.1:8080). Override per request with ?proxy=<type>");
+    eprintln!("    or the X-Github-Proxy header");
+    eprintln!("    --stream fetches the content and streams it back instead of redirecting,");
+    eprintln!("    --upstream replaces the proxy's origin and --max-size limits the body size");
+    eprintln!("    --cache serves streamed bodies from the local cache, --cache-size bounds it");
+    eprintln!("    Access options: --allow-owners, --deny-owners, --allow-repos (owner/repo),");
+    eprintln!("    --deny-repos and --allow-kinds (file, release, archive, repository, tree,");
+    eprintln!(
+        "    commit, pull, compare, api) take comma separated lists, --rate-limit <n> allows"
+    );
+    eprintln!("    n requests per minute per client");
     eprintln!();
     eprintln!("Examples:");
     eprintln!("  github-proxy xget file easy-install easy-install main install.sh");
@@ -95,4 +826,5 @@ fn print_usage() {
     eprintln!(
         "  github-proxy gh-proxy release easy-install easy-install nightly ei-aarch64-apple-darwin.tar.gz"
     );
+    eprintln!("  github-proxy xget image ghcr.io/owner/image:1.0");
 }
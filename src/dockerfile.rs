@@ -0,0 +1,71 @@
+use crate::Proxy;
+use crate::rewrite::{Change, replace_urls, retarget_url};
+
+/// Instructions whose arguments may download from GitHub
+const INSTRUCTIONS: [&str; 3] = ["ADD", "RUN", "ARG"];
+
+/// Rewrite GitHub URLs in the `ADD`, `RUN` and `ARG` instructions of a Dockerfile
+///
+/// Both canonical and proxied URLs are recognised, so passing `Proxy::Github`
+/// restores canonical URLs in a mirrored Dockerfile. Other instructions,
+/// comments and formatting are left untouched.
+pub fn rewrite_dockerfile(text: &str, proxy: &Proxy) -> (String, Vec<Change>) {
+    let escape = escape_char(text);
+    let mut changes = Vec::new();
+    let mut out = String::with_capacity(text.len());
+    // Instruction of the current logical line, carried over continuations
+    let mut current: Option<String> = None;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let trimmed = content.trim_start();
+
+        // Comments and blank lines don't end a continued instruction
+        if trimmed.starts_with('#') || trimmed.is_empty() {
+            out.push_str(line);
+            continue;
+        }
+
+        let instruction = match &current {
+            Some(instruction) => instruction.clone(),
+            None => trimmed
+                .split(|c: char| c.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase(),
+        };
+
+        if INSTRUCTIONS.contains(&instruction.as_str()) {
+            out.push_str(&replace_urls(line, |url| {
+                let new = retarget_url(url, proxy)?;
+                changes.push(Change {
+                    line: i + 1,
+                    from: url.to_string(),
+                    to: new.clone(),
+                });
+                Some(new)
+            }));
+        } else {
+            out.push_str(line);
+        }
+
+        current = content.ends_with(escape).then_some(instruction);
+    }
+
+    (out, changes)
+}
+
+/// Read the `# escape=` parser directive, defaulting to backslash
+fn escape_char(text: &str) -> char {
+    for line in text.lines() {
+        let Some(directive) = line.trim().strip_prefix('#') else {
+            break;
+        };
+        if let Some((key, value)) = directive.split_once('=')
+            && key.trim().eq_ignore_ascii_case("escape")
+        {
+            return value.trim().chars().next().unwrap_or('\\');
+        }
+    }
+    '\\'
+}
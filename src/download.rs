@@ -0,0 +1,308 @@
+use crate::{ConversionError, LfsPointer, Proxy, ProxyChain, Resource};
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+/// Download a resource, trying each proxy of the chain in order
+///
+/// Redirects are followed and the body is streamed to `path`. Connection
+/// errors, 4xx/5xx responses and timeouts move on to the next proxy.
+/// Returns the proxy that served the file.
+///
+/// A Git LFS pointer served by a proxy that doesn't resolve LFS counts as a
+/// failure, so the next proxy is asked for the real content.
+///
+/// The body is written to `<path>.part` first, with the size and ETag the
+/// server reported in `<path>.part.id`. An interrupted transfer is resumed
+/// from there with a `Range` request, on the next proxy or in a later call,
+/// as long as the server reports the same size and ETag. A partial file
+/// without a known identity is downloaded again from the start.
+pub fn fetch(
+    resource: &Resource,
+    chain: &ProxyChain,
+    path: impl AsRef<Path>,
+) -> Result<Proxy, ConversionError> {
+    check_downloadable(resource)?;
+    let path = path.as_ref();
+    let agent = agent(chain);
+    let mut attempts = Vec::new();
+
+    for (proxy, url) in chain.urls(resource) {
+        match download(&agent, &url, path, proxy.resolves_lfs()) {
+            Ok(()) => return Ok(proxy),
+            Err(e) => attempts.push((proxy, format!("{}: {}", url, e))),
+        }
+    }
+
+    Err(ConversionError::DownloadFailed(attempts))
+}
+
+/// Stall timeout of racing requests when the chain doesn't set one
+const RACE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Download a resource by requesting it from every proxy of the chain at once
+///
+/// The first proxy to answer with a successful response is streamed to
+/// `path` and returned. As soon as it is picked, the other responses are
+/// dropped, closing their connections, and requests still pending are
+/// dropped when they answer. If the winner fails while streaming, the
+/// remaining proxies are tried one by one as in [`fetch`], resuming the
+/// partial file where possible. Requests give up after 30 seconds without
+/// progress unless the chain sets its own timeout.
+pub fn race(
+    resource: &Resource,
+    chain: &ProxyChain,
+    path: impl AsRef<Path>,
+) -> Result<Proxy, ConversionError> {
+    check_downloadable(resource)?;
+    let path = path.as_ref();
+    let timeout = chain.timeout().unwrap_or(RACE_TIMEOUT);
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .build();
+    let picked = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+
+    let urls = chain.urls(resource);
+    for (proxy, url) in urls.clone() {
+        let agent = agent.clone();
+        let picked = picked.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let result = agent.get(&url).call().map_err(describe);
+            // A late response is dropped right away instead of being queued
+            if !picked.load(Ordering::SeqCst) {
+                let _ = tx.send((proxy, url, result));
+            }
+        });
+    }
+    drop(tx);
+
+    let mut attempts = Vec::new();
+    for (proxy, url, result) in &rx {
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                attempts.push((proxy, format!("{}: {}", url, e)));
+                continue;
+            }
+        };
+
+        picked.store(true, Ordering::SeqCst);
+        for _ in rx.try_iter() {}
+        let identity = Identity::of(&response, 0);
+        match save(response, path, false, &identity, proxy.resolves_lfs()) {
+            Ok(()) => return Ok(proxy),
+            Err(e) => attempts.push((proxy, format!("{}: {}", url, e))),
+        }
+
+        // Fall back to the proxies that haven't failed yet, in chain order
+        for (proxy, url) in urls {
+            if attempts.iter().any(|(failed, _)| *failed == proxy) {
+                continue;
+            }
+            match download(&agent, &url, path, proxy.resolves_lfs()) {
+                Ok(()) => return Ok(proxy),
+                Err(e) => attempts.push((proxy, format!("{}: {}", url, e))),
+            }
+        }
+        break;
+    }
+
+    Err(ConversionError::DownloadFailed(attempts))
+}
+
+/// Images have no URL to download, only a reference to pull
+pub(crate) fn check_downloadable(resource: &Resource) -> Result<(), ConversionError> {
+    match resource.pull_reference(&Proxy::Github) {
+        Some(reference) => Err(ConversionError::ImageNotDownloadable(reference)),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn agent(chain: &ProxyChain) -> ureq::Agent {
+    let mut builder = ureq::AgentBuilder::new();
+    if let Some(timeout) = chain.timeout() {
+        builder = builder.timeout_connect(timeout).timeout_read(timeout);
+    }
+    builder.build()
+}
+
+/// Size and ETag a server reports for the whole file
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct Identity {
+    length: Option<u64>,
+    etag: Option<String>,
+}
+
+impl Identity {
+    fn of(response: &ureq::Response, offset: u64) -> Identity {
+        let length = match response.status() {
+            // Content-Range: bytes 500-999/1000
+            206 => response
+                .header("Content-Range")
+                .and_then(|range| range.rsplit('/').next())
+                .and_then(|total| total.parse().ok()),
+            _ => response
+                .header("Content-Length")
+                .and_then(|len| len.parse::<u64>().ok())
+                .map(|len| len + offset),
+        };
+        Identity {
+            length,
+            etag: response.header("ETag").map(|etag| etag.to_string()),
+        }
+    }
+
+    /// Whether both describe the same file, judged by at least one known value
+    fn matches(&self, other: &Identity) -> bool {
+        let comparable = (self.length.is_some() && other.length.is_some())
+            || (self.etag.is_some() && other.etag.is_some());
+        comparable && agree(&self.length, &other.length) && agree(&self.etag, &other.etag)
+    }
+
+    /// Read the identity stored next to a partial file
+    fn load(path: &Path) -> Option<Identity> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let mut lines = text.lines();
+        let length = lines.next()?;
+        let etag = lines.next()?;
+        Some(Identity {
+            length: (length != "-").then(|| length.parse().ok()).flatten(),
+            etag: (etag != "-").then(|| etag.to_string()),
+        })
+    }
+
+    fn store(&self, path: &Path) -> io::Result<()> {
+        let length = self
+            .length
+            .map_or("-".to_string(), |length| length.to_string());
+        std::fs::write(
+            path,
+            format!("{}\n{}\n", length, self.etag.as_deref().unwrap_or("-")),
+        )
+    }
+}
+
+/// Unknown values can't contradict each other
+fn agree<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    a.is_none() || b.is_none() || a == b
+}
+
+fn download(agent: &ureq::Agent, url: &str, path: &Path, resolves_lfs: bool) -> Result<(), String> {
+    // Without a recorded identity nothing tells whether the bytes still match
+    let known = Identity::load(&id_path(path));
+    let offset = match known {
+        Some(_) => std::fs::metadata(part_path(path)).map_or(0, |m| m.len()),
+        None => 0,
+    };
+    let mut response = request(agent, url, offset)?;
+    let mut current = Identity::of(&response, 0);
+
+    let resumable = offset > 0
+        && response.status() == 206
+        && content_range_start(&response) == Some(offset)
+        && known.is_some_and(|known| known.matches(&current));
+    if offset > 0 && !resumable && response.status() == 206 {
+        // The partial file belongs to different content, start over
+        response = request(agent, url, 0)?;
+        current = Identity::of(&response, 0);
+    }
+
+    save(response, path, resumable, &current, resolves_lfs)
+}
+
+fn request(agent: &ureq::Agent, url: &str, offset: u64) -> Result<ureq::Response, String> {
+    let mut request = agent.get(url);
+    if offset > 0 {
+        request = request.set("Range", &format!("bytes={}-", offset));
+    }
+    request.call().map_err(describe)
+}
+
+fn content_range_start(response: &ureq::Response) -> Option<u64> {
+    let range = response.header("Content-Range")?.strip_prefix("bytes ")?;
+    range.split('-').next()?.parse().ok()
+}
+
+/// Stream a response into `<path>.part`, renaming it to `path` once complete
+///
+/// An incomplete partial file is kept, together with the identity of the
+/// file, so the transfer can be resumed. Unless the proxy resolves LFS, an
+/// LFS pointer is deleted and reported as an error.
+fn save(
+    response: ureq::Response,
+    path: &Path,
+    append: bool,
+    identity: &Identity,
+    resolves_lfs: bool,
+) -> Result<(), String> {
+    let part = part_path(path);
+    let id = id_path(path);
+    if !append {
+        identity.store(&id).map_err(|e| e.to_string())?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&part)
+        .map_err(|e| e.to_string())?;
+
+    io::copy(&mut response.into_reader(), &mut file).map_err(|e| e.to_string())?;
+    drop(file);
+
+    let size = std::fs::metadata(&part).map_err(|e| e.to_string())?.len();
+    if let Some(total) = identity.length.filter(|total| *total != size) {
+        return Err(format!("incomplete transfer: {} of {} bytes", size, total));
+    }
+    if !resolves_lfs
+        && size <= LfsPointer::MAX_SIZE
+        && let Some(pointer) = std::fs::read(&part)
+            .ok()
+            .and_then(|content| LfsPointer::parse(&content))
+    {
+        let _ = std::fs::remove_file(&part);
+        let _ = std::fs::remove_file(&id);
+        return Err(lfs_pointer_error(&pointer));
+    }
+    std::fs::rename(&part, path).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&id);
+    Ok(())
+}
+
+pub(crate) fn lfs_pointer_error(pointer: &LfsPointer) -> String {
+    format!(
+        "served a Git LFS pointer instead of the {} byte file",
+        pointer.size
+    )
+}
+
+/// Short description of a request error, without repeating the URL
+pub(crate) fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(code, _) => format!("status {}", code),
+        ureq::Error::Transport(transport) => match transport.message() {
+            Some(message) => format!("{}: {}", transport.kind(), message),
+            None => transport.kind().to_string(),
+        },
+    }
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Size and ETag of the file being written to the partial file
+fn id_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part.id");
+    PathBuf::from(name)
+}
@@ -1,3 +1,4 @@
+use crate::Proxy;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,17 +8,66 @@ pub enum ConversionError {
     )]
     InvalidProxyType(String),
 
-    #[error("Invalid resource type: {0}\nSupported types: file, release")]
+    #[error("Invalid resource type: {0}\nSupported types: file, release, tree, image")]
     InvalidResourceType(String),
 
+    #[error(
+        "Invalid target: {0}\nUse a target triple such as x86_64-unknown-linux-musl or an OS/arch pair such as linux-amd64"
+    )]
+    InvalidTarget(String),
+
     #[error("Invalid arguments: {0}")]
     InvalidArguments(String),
 
     #[error(
-        "Invalid GitHub URL: {0}\nSupported formats:\n  - https://github.com/{{owner}}/{{repo}}/raw/{{ref}}/{{path}}\n  - https://github.com/{{owner}}/{{repo}}/blob/{{ref}}/{{path}}\n  - https://github.com/{{owner}}/{{repo}}/releases/download/{{tag}}/{{filename}}"
+        "Invalid GitHub URL: {0}\nSupported formats:\n  - https://github.com/{{owner}}/{{repo}}/raw/{{ref}}/{{path}}\n  - https://github.com/{{owner}}/{{repo}}/blob/{{ref}}/{{path}}\n  - https://github.com/{{owner}}/{{repo}}/releases/download/{{tag}}/{{filename}}\n  - https://github.com/{{owner}}/{{repo}}/archive/{{ref}}.{{zip,tar.gz}}\n  - https://github.com/{{owner}}/{{repo}}/tree/{{ref}}/{{dir}}\n  - https://api.github.com/repos/{{owner}}/{{repo}}/{{releases/latest,releases/tags/tag,contents/path,git/trees/ref}}\n  - https://github.com/{{owner}}/{{repo}}/{{commit/sha,pull/number,compare/base...head}}.{{patch,diff}}\n  - https://raw.githubusercontent.com/{{owner}}/{{repo}}/{{ref}}/{{path}}\n  - https://media.githubusercontent.com/media/{{owner}}/{{repo}}/{{ref}}/{{path}}\n  - https://github.com/{{owner}}/{{repo}}(.git)\n  - ghcr.io/{{owner}}/{{image}}(:{{tag}}|@sha256:{{digest}})"
     )]
     InvalidUrl(String),
 
+    #[error("{proxy} proxy does not support {kind} resources")]
+    UnsupportedResource { proxy: Proxy, kind: &'static str },
+
     #[error("URL parse error: {0}")]
     ParseError(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Download failed{}", format_attempts(.0))]
+    DownloadFailed(Vec<(Proxy, String)>),
+
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("No asset of release {tag} matches {selector}")]
+    AssetNotFound { tag: String, selector: String },
+
+    #[error("No checksum found for {0}")]
+    ChecksumNotFound(String),
+
+    #[error("{0} is a container image, pull it instead of downloading it")]
+    ImageNotDownloadable(String),
+
+    #[error("Upstream returned {0}")]
+    UpstreamStatus(u16),
+
+    #[error("Body exceeds the limit of {0} bytes")]
+    TooLarge(u64),
+
+    #[error("{proxy} served different content: expected SHA-256 {expected}, got {actual}")]
+    ContentMismatch {
+        proxy: Proxy,
+        expected: String,
+        actual: String,
+    },
+}
+
+fn format_attempts(attempts: &[(Proxy, String)]) -> String {
+    if attempts.is_empty() {
+        return ": no proxy in the chain supports this resource".to_string();
+    }
+    attempts
+        .iter()
+        .map(|(proxy, error)| format!("\n  {}: {}", proxy, error))
+        .collect()
 }
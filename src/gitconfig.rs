@@ -0,0 +1,66 @@
+use crate::Proxy;
+use strum::IntoEnumIterator;
+
+const GITHUB: &str = "https://github.com/";
+
+/// Render the `url.<base>.insteadOf` rule that sends github.com clones through `proxy`
+///
+/// Returns None if the proxy can't be used with git. `Proxy::Github` needs no
+/// rule and yields an empty snippet.
+pub fn gitconfig_snippet(proxy: &Proxy) -> Option<String> {
+    let prefix = proxy.clone_prefix()?;
+    if *proxy == Proxy::Github {
+        return Some(String::new());
+    }
+    Some(format!("[url \"{}\"]\n\tinsteadOf = {}\n", prefix, GITHUB))
+}
+
+/// Add the rule for `proxy` to the contents of a gitconfig file
+///
+/// Rules previously added for any other proxy are removed first, so applying
+/// is idempotent and switching proxies doesn't leave conflicting rules behind.
+pub fn apply_gitconfig(text: &str, proxy: &Proxy) -> Option<String> {
+    let snippet = gitconfig_snippet(proxy)?;
+    let mut out = remove_gitconfig(text);
+    if !snippet.is_empty() && !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&snippet);
+    Some(out)
+}
+
+/// Remove every `url.<base>` section whose base is a known proxy prefix
+///
+/// Only the section header and its variables are removed. Comments and blank
+/// lines may belong to whatever follows, so they are kept.
+pub fn remove_gitconfig(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut skipping = false;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            skipping = section_url(trimmed).is_some_and(is_proxy_prefix);
+        }
+        let comment = trimmed.is_empty() || trimmed.starts_with(['#', ';']);
+        if !skipping || comment {
+            out.push_str(line);
+        }
+    }
+
+    out
+}
+
+/// Extract `<base>` from a `[url "<base>"]` section header
+fn section_url(header: &str) -> Option<&str> {
+    let inner = header.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let base = inner.strip_prefix("url")?.trim_start();
+    base.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn is_proxy_prefix(base: &str) -> bool {
+    Proxy::iter()
+        .filter(|p| *p != Proxy::Github)
+        .filter_map(|p| p.clone_prefix())
+        .any(|prefix| prefix == base)
+}
@@ -0,0 +1,83 @@
+use crate::Proxy;
+
+/// Container registries xget mirrors, with the name of their `/cr/` route
+const XGET_REGISTRIES: [(&str, &str); 1] = [("ghcr.io", "ghcr")];
+
+/// Host and path that replace `registry` in image references pulled through `proxy`
+///
+/// Returns None if the proxy doesn't mirror the registry.
+pub(crate) fn pull_prefix(proxy: &Proxy, registry: &str) -> Option<String> {
+    match proxy {
+        Proxy::Github => Some(registry.to_string()),
+        Proxy::Xget => Some(format!("xget.xi-xu.me/cr/{}", xget_route(registry)?)),
+        // Only xget mirrors container registries
+        Proxy::GhProxy | Proxy::Jsdelivr | Proxy::Statically => None,
+    }
+}
+
+/// Registry behind an xget `/cr/` route, e.g. `ghcr.io` for `ghcr`
+pub(crate) fn xget_registry(route: &str) -> Option<&'static str> {
+    XGET_REGISTRIES
+        .iter()
+        .find(|(_, r)| *r == route)
+        .map(|(registry, _)| *registry)
+}
+
+fn xget_route(registry: &str) -> Option<&'static str> {
+    XGET_REGISTRIES
+        .iter()
+        .find(|(r, _)| *r == registry)
+        .map(|(_, route)| *route)
+}
+
+/// Registry API root through which `proxy` mirrors `registry`, for containerd
+///
+/// Returns None if the proxy doesn't mirror the registry. `Proxy::Github`
+/// is the registry itself and needs no mirror either.
+pub fn registry_mirror(proxy: &Proxy, registry: &str) -> Option<String> {
+    match proxy {
+        // xget expects the route inside the repository name: /v2/cr/ghcr/owner/image/...
+        Proxy::Xget => Some(format!(
+            "https://xget.xi-xu.me/v2/cr/{}",
+            xget_route(registry)?
+        )),
+        Proxy::Github | Proxy::GhProxy | Proxy::Jsdelivr | Proxy::Statically => None,
+    }
+}
+
+/// Render `/etc/containerd/certs.d/<registry>/hosts.toml` pulling `registry` through `mirror`
+///
+/// A mirror URL that already contains the `/v2` API root, like those from
+/// [`registry_mirror`], is used as is with `override_path`. Otherwise
+/// containerd appends `/v2` itself. Pulls fall back to the registry when the
+/// mirror fails.
+pub fn containerd_hosts_snippet(registry: &str, mirror: &str) -> String {
+    let mirror = mirror.trim_end_matches('/');
+    let mut snippet = format!(
+        "server = \"https://{}\"\n\n[host.\"{}\"]\n  capabilities = [\"pull\", \"resolve\"]\n",
+        registry, mirror
+    );
+    let path = mirror.split_once("://").map_or(mirror, |(_, rest)| rest);
+    if path.contains("/v2/") || path.ends_with("/v2") {
+        snippet.push_str("  override_path = true\n");
+    }
+    snippet
+}
+
+/// Render a Docker `daemon.json` using `mirror` as registry mirror
+///
+/// Docker only consults `registry-mirrors` for Docker Hub images and
+/// rejects mirrors with a path, so this doesn't apply to ghcr.io or xget's
+/// routes. Pull those by their mirrored reference, or through containerd
+/// with [`containerd_hosts_snippet`]. Returns None for mirrors with a path.
+pub fn daemon_json_snippet(mirror: &str) -> Option<String> {
+    let mirror = mirror.trim_end_matches('/');
+    let host = mirror.split_once("://").map_or(mirror, |(_, rest)| rest);
+    if host.contains('/') {
+        return None;
+    }
+    Some(format!(
+        "{{\n  \"registry-mirrors\": [\"{}\"]\n}}\n",
+        mirror
+    ))
+}
@@ -0,0 +1,52 @@
+/// Pointer file Git LFS stores in the repository in place of the content
+///
+/// Proxies serving files from the git tree rather than through GitHub
+/// return this pointer instead of the file, see [`crate::Proxy::resolves_lfs`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LfsPointer {
+    /// SHA-256 of the real content
+    pub oid: String,
+    /// Size of the real content in bytes
+    pub size: u64,
+}
+
+impl LfsPointer {
+    /// Pointer files are never larger than this
+    pub const MAX_SIZE: u64 = 1024;
+
+    /// Parse a pointer file, None if `content` is anything else
+    ///
+    /// ```text
+    /// version https://git-lfs.github.com/spec/v1
+    /// oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+    /// size 12345
+    /// ```
+    pub fn parse(content: &[u8]) -> Option<LfsPointer> {
+        if content.len() as u64 > Self::MAX_SIZE {
+            return None;
+        }
+        let text = std::str::from_utf8(content).ok()?;
+        let mut lines = text.lines();
+        let version = lines.next()?.strip_prefix("version ")?;
+        // Pointers written by early releases name the spec under its old host
+        if !version.starts_with("https://git-lfs.github.com/spec/")
+            && !version.starts_with("https://hawser.github.com/spec/")
+        {
+            return None;
+        }
+
+        let (mut oid, mut size) = (None, None);
+        for line in lines {
+            if let Some(hex) = line.strip_prefix("oid sha256:") {
+                oid = Some(hex)
+                    .filter(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+            } else if let Some(bytes) = line.strip_prefix("size ") {
+                size = bytes.parse().ok();
+            }
+        }
+        Some(LfsPointer {
+            oid: oid?.to_ascii_lowercase(),
+            size: size?,
+        })
+    }
+}
@@ -1,14 +1,61 @@
+mod asset;
+#[cfg(feature = "download")]
+mod cache;
+mod cargo;
+#[cfg(feature = "download")]
+mod checksum;
 pub mod cli;
+mod dockerfile;
+#[cfg(feature = "download")]
+mod download;
 mod error;
+mod gitconfig;
+mod image;
+mod lfs;
+#[cfg(feature = "server")]
+mod policy;
+#[cfg(feature = "download")]
+mod probe;
 mod proxy;
+mod ranking;
+#[cfg(feature = "resolve")]
+mod resolve;
 mod resource;
+mod rewrite;
+#[cfg(feature = "server")]
+mod server;
+mod workflow;
+pub use asset::{Arch, AssetSelector, Os, Target, select_asset};
+#[cfg(feature = "download")]
+pub use cache::{Cache, CacheEntry, Cached};
+pub use cargo::{cargo_source_config, rewrite_cargo};
+#[cfg(feature = "download")]
+pub use checksum::{Audit, audit, discover_sha256, sha256_file, sha256_reader, verify_sha256};
+pub use dockerfile::rewrite_dockerfile;
+#[cfg(feature = "download")]
+pub use download::{fetch, race};
 pub use error::ConversionError;
-pub use proxy::Proxy;
-pub use resource::Resource;
+pub use gitconfig::{apply_gitconfig, gitconfig_snippet, remove_gitconfig};
+pub use image::{containerd_hosts_snippet, daemon_json_snippet, registry_mirror};
+pub use lfs::LfsPointer;
+#[cfg(feature = "server")]
+pub use policy::{Policy, RateLimit};
+#[cfg(feature = "download")]
+pub use probe::{PROBE_BYTES, Probe, probe};
+pub use proxy::{Proxy, ProxyChain};
+pub use ranking::{FAILURE_COOLDOWN, RANKING_TTL, Ranking};
+#[cfg(feature = "resolve")]
+pub use resolve::{ReleaseInfo, release_info, resolve_release};
+pub use resource::{ApiEndpoint, ArchiveFormat, PatchFormat, ReferenceKind, Resource};
+pub use rewrite::{Change, rewrite_diff, rewrite_text};
+#[cfg(feature = "server")]
+pub use server::{PROXY_HEADER, Reply, ServerConfig, redirect, serve};
+pub use workflow::rewrite_workflow;
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr as _;
+    use std::time::Duration;
 
     use super::*;
 
@@ -304,4 +351,2029 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_parse_raw_githubusercontent_url() {
+        let url = "https://raw.githubusercontent.com/owner/repo/refs/heads/main/install.sh";
+        let resource = Resource::try_from(url).unwrap();
+        assert_eq!(
+            resource,
+            Resource::file(
+                "owner".to_string(),
+                "repo".to_string(),
+                "refs/heads/main".to_string(),
+                "install.sh".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_archive_url() {
+        let url = "https://github.com/owner/repo/archive/refs/tags/v1.0.0.tar.gz";
+        let resource = Resource::try_from(url).unwrap();
+        assert_eq!(
+            resource,
+            Resource::archive(
+                "owner".to_string(),
+                "repo".to_string(),
+                "refs/tags/v1.0.0".to_string(),
+                ArchiveFormat::TarGz
+            )
+        );
+        assert_eq!(
+            resource.url(&Proxy::Xget).unwrap(),
+            "https://xget.xi-xu.me/gh/owner/repo/archive/refs/tags/v1.0.0.tar.gz"
+        );
+        assert!(resource.url(&Proxy::Jsdelivr).is_none());
+    }
+
+    #[test]
+    fn test_rewrite_text() {
+        let text = r#"curl -fsSL https://github.com/owner/repo/raw/main/install.sh | sh
+wget "https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz"
+See [docs](https://github.com/owner/repo/blob/main/README.md).
+https://github.com/owner/repo/issues/1
+"#;
+        let expected = r#"curl -fsSL https://gh-proxy.com/https://github.com/owner/repo/raw/main/install.sh | sh
+wget "https://gh-proxy.com/https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz"
+See [docs](https://gh-proxy.com/https://github.com/owner/repo/raw/main/README.md).
+https://github.com/owner/repo/issues/1
+"#;
+        assert_eq!(rewrite_text(text, &Proxy::GhProxy), expected);
+        // Already proxied URLs are not proxied twice
+        assert_eq!(rewrite_text(expected, &Proxy::GhProxy), expected);
+    }
+
+    #[test]
+    fn test_rewrite_text_unsupported_left_alone() {
+        let text = "ADD https://github.com/owner/repo/releases/download/v1/app.tar.gz /app\n";
+        assert_eq!(rewrite_text(text, &Proxy::Jsdelivr), text);
+    }
+
+    #[test]
+    fn test_rewrite_diff() {
+        let old = "a\nhttps://github.com/o/r/raw/main/x\n";
+        let new = rewrite_text(old, &Proxy::Xget);
+        assert_eq!(
+            rewrite_diff("install.sh", old, &new),
+            "--- install.sh\n+++ install.sh\n@@ -1,2 +1,2 @@\n a\n-https://github.com/o/r/raw/main/x\n+https://xget.xi-xu.me/gh/o/r/raw/main/x\n"
+        );
+        assert_eq!(rewrite_diff("install.sh", old, old), "");
+
+        // Nearby changes share a hunk with context, distant ones get their own
+        let old = "1\nA\n3\nB\n5\n6\n7\n8\n9\n10\n11\nC\n";
+        let new = "1\na\n3\nb\n5\n6\n7\n8\n9\n10\n11\nc\n";
+        assert_eq!(
+            rewrite_diff("f", old, new),
+            "--- f\n+++ f\n@@ -1,7 +1,7 @@\n 1\n-A\n+a\n 3\n-B\n+b\n 5\n 6\n 7\n@@ -9,4 +9,4 @@\n 9\n 10\n 11\n-C\n+c\n"
+        );
+    }
+
+    #[test]
+    fn test_from_proxied_url() {
+        let cases = [
+            (
+                Proxy::Github,
+                "https://github.com/owner/repo/raw/main/src/lib.rs",
+            ),
+            (
+                Proxy::GhProxy,
+                "https://gh-proxy.com/https://github.com/owner/repo/raw/main/src/lib.rs",
+            ),
+            (
+                Proxy::Xget,
+                "https://xget.xi-xu.me/gh/owner/repo/raw/main/src/lib.rs",
+            ),
+            (
+                Proxy::Jsdelivr,
+                "https://cdn.jsdelivr.net/gh/owner/repo@main/src/lib.rs",
+            ),
+            (
+                Proxy::Statically,
+                "https://cdn.statically.io/gh/owner/repo/main/src/lib.rs",
+            ),
+        ];
+        let expected = Resource::file(
+            "owner".to_string(),
+            "repo".to_string(),
+            "main".to_string(),
+            "src/lib.rs".to_string(),
+        );
+        for (proxy, url) in cases {
+            assert_eq!(
+                Resource::from_proxied_url(url).unwrap(),
+                (proxy, expected.clone())
+            );
+            assert_eq!(expected.url(&proxy).unwrap(), url);
+        }
+
+        // Blob pages don't round-trip, they come back as raw downloads
+        let blob = "https://github.com/owner/repo/blob/main/src/lib.rs";
+        let proxied = rewrite::retarget_url(blob, &Proxy::Xget).unwrap();
+        assert_eq!(
+            proxied,
+            "https://xget.xi-xu.me/gh/owner/repo/raw/main/src/lib.rs"
+        );
+        assert_eq!(
+            rewrite::retarget_url(&proxied, &Proxy::Github).unwrap(),
+            "https://github.com/owner/repo/raw/main/src/lib.rs"
+        );
+
+        // Other URLs survive a round trip through every proxy that supports
+        // them, while directory and repository links are left alone
+        let urls = [
+            "https://github.com/owner/repo/raw/main/src/lib.rs",
+            "https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz",
+            "https://github.com/owner/repo/archive/refs/tags/v1.0.0.tar.gz",
+        ];
+        for url in urls {
+            for proxy in [Proxy::GhProxy, Proxy::Xget] {
+                let proxied = rewrite::retarget_url(url, &proxy).unwrap();
+                assert_eq!(
+                    rewrite::retarget_url(&proxied, &Proxy::Github).unwrap(),
+                    url
+                );
+            }
+        }
+        for url in [
+            "https://github.com/owner/repo/tree/main/src",
+            "https://github.com/owner/repo",
+        ] {
+            for proxy in [Proxy::GhProxy, Proxy::Xget, Proxy::Jsdelivr] {
+                assert!(rewrite::retarget_url(url, &proxy).is_none());
+            }
+        }
+        let text = "ADD https://github.com/owner/repo/tree/main/src /src\n";
+        assert_eq!(rewrite_dockerfile(text, &Proxy::Xget).0, text);
+    }
+
+    #[test]
+    fn test_rewrite_dockerfile() {
+        let text = r#"# syntax=docker/dockerfile:1
+FROM alpine
+ARG INSTALLER=https://github.com/owner/repo/raw/main/install.sh
+ADD https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz /tmp/
+RUN apk add curl && \
+    curl -fsSL https://github.com/owner/repo/raw/main/setup.sh | sh
+LABEL source=https://github.com/owner/repo/raw/main/install.sh
+"#;
+        let (proxied, changes) = rewrite_dockerfile(text, &Proxy::Xget);
+        assert_eq!(
+            proxied,
+            r#"# syntax=docker/dockerfile:1
+FROM alpine
+ARG INSTALLER=https://xget.xi-xu.me/gh/owner/repo/raw/main/install.sh
+ADD https://xget.xi-xu.me/gh/owner/repo/releases/download/v1.0.0/app.tar.gz /tmp/
+RUN apk add curl && \
+    curl -fsSL https://xget.xi-xu.me/gh/owner/repo/raw/main/setup.sh | sh
+LABEL source=https://github.com/owner/repo/raw/main/install.sh
+"#
+        );
+        assert_eq!(
+            changes.iter().map(|c| c.line).collect::<Vec<_>>(),
+            vec![3, 4, 6]
+        );
+
+        // Un-proxy restores the original file
+        let (restored, changes) = rewrite_dockerfile(&proxied, &Proxy::Github);
+        assert_eq!(restored, text);
+        assert_eq!(changes.len(), 3);
+
+        // Blob links are fetched as the raw file, like in every other rewriter
+        let (proxied, _) = rewrite_dockerfile(
+            "ADD https://github.com/owner/repo/blob/main/app.conf /etc/\n",
+            &Proxy::Xget,
+        );
+        assert_eq!(
+            proxied,
+            "ADD https://xget.xi-xu.me/gh/owner/repo/raw/main/app.conf /etc/\n"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_workflow() {
+        let text = r#"name: CI
+# https://github.com/owner/repo/raw/main/comment.sh
+on: [push]
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - run: curl -fsSL https://github.com/owner/repo/raw/main/a.sh | sh # https://github.com/owner/repo/raw/main/keep.sh
+      - name: Install
+        run: |
+          # fetch the release
+          curl -LO https://github.com/owner/repo/releases/download/v1/app.tar.gz
+
+          tar xf app.tar.gz
+      - uses: some/action@v1
+        with:
+          url: "https://github.com/owner/repo/raw/main/b.sh"
+          script: >
+            wget https://github.com/owner/repo/raw/main/c.sh
+        env:
+          URL: https://github.com/owner/repo/raw/main/env.sh
+"#;
+        let (out, changes) = rewrite_workflow(text, &Proxy::GhProxy);
+        assert_eq!(
+            changes.iter().map(|c| c.line).collect::<Vec<_>>(),
+            vec![9, 13, 18, 20]
+        );
+        let gh = "https://gh-proxy.com/https://github.com";
+        assert!(out.contains(&format!("run: curl -fsSL {}/owner/repo/raw/main/a.sh | sh # https://github.com/owner/repo/raw/main/keep.sh", gh)));
+        assert!(out.contains(&format!("url: \"{}/owner/repo/raw/main/b.sh\"", gh)));
+        assert!(out.contains("# https://github.com/owner/repo/raw/main/comment.sh\n"));
+        assert!(out.contains("URL: https://github.com/owner/repo/raw/main/env.sh\n"));
+        assert_eq!(rewrite_workflow(&out, &Proxy::Github).0, text);
+
+        // Blob links are fetched as the raw file, like in every other rewriter
+        let (out, _) = rewrite_workflow(
+            "steps:\n  - run: curl -O https://github.com/owner/repo/blob/main/a.sh\n",
+            &Proxy::GhProxy,
+        );
+        assert_eq!(
+            out,
+            format!("steps:\n  - run: curl -O {}/owner/repo/raw/main/a.sh\n", gh)
+        );
+    }
+
+    #[test]
+    fn test_gitconfig_snippet() {
+        assert_eq!(
+            gitconfig_snippet(&Proxy::GhProxy).unwrap(),
+            "[url \"https://gh-proxy.com/https://github.com/\"]\n\tinsteadOf = https://github.com/\n"
+        );
+        assert_eq!(gitconfig_snippet(&Proxy::Github).unwrap(), "");
+        assert!(gitconfig_snippet(&Proxy::Jsdelivr).is_none());
+    }
+
+    #[test]
+    fn test_apply_and_remove_gitconfig() {
+        let text = "[user]\n\tname = me\n[url \"git@github.com:\"]\n\tpushInsteadOf = https://github.com/\n";
+        let applied = apply_gitconfig(text, &Proxy::GhProxy).unwrap();
+        assert_eq!(
+            applied,
+            format!("{}{}", text, gitconfig_snippet(&Proxy::GhProxy).unwrap())
+        );
+
+        // Switching proxies replaces the previous rule
+        let switched = apply_gitconfig(&applied, &Proxy::Xget).unwrap();
+        assert_eq!(
+            switched,
+            format!("{}{}", text, gitconfig_snippet(&Proxy::Xget).unwrap())
+        );
+
+        assert_eq!(remove_gitconfig(&switched), text);
+        assert_eq!(apply_gitconfig(&switched, &Proxy::Github).unwrap(), text);
+
+        // Comments after a removed section are kept
+        let commented = format!("{}# keep me\n[core]\n", applied);
+        assert_eq!(
+            remove_gitconfig(&commented),
+            format!("{}# keep me\n[core]\n", text)
+        );
+    }
+
+    #[test]
+    fn test_repository_resource() {
+        for url in [
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo.git",
+            "https://github.com/owner/repo/",
+        ] {
+            assert_eq!(
+                Resource::try_from(url).unwrap(),
+                Resource::repository("owner".to_string(), "repo".to_string())
+            );
+        }
+        let resource = Resource::repository("owner".to_string(), "repo".to_string());
+        assert_eq!(
+            resource.url(&Proxy::GhProxy).unwrap(),
+            "https://gh-proxy.com/https://github.com/owner/repo"
+        );
+        assert!(resource.url(&Proxy::Statically).is_none());
+        // Repository links in prose are not rewritten
+        let text = "Repository: https://github.com/owner/repo";
+        assert_eq!(rewrite_text(text, &Proxy::GhProxy), text);
+    }
+
+    #[test]
+    fn test_rewrite_cargo() {
+        let manifest = r#"[dependencies]
+foo = { git = "https://github.com/owner/foo", branch = "main" }
+bar = "1"
+
+[dependencies.baz]
+git = "https://github.com/owner/baz.git"
+"#;
+        let (out, changes) = rewrite_cargo(manifest, &Proxy::Xget);
+        assert_eq!(
+            out,
+            r#"[dependencies]
+foo = { git = "https://xget.xi-xu.me/gh/owner/foo", branch = "main" }
+bar = "1"
+
+[dependencies.baz]
+git = "https://xget.xi-xu.me/gh/owner/baz.git"
+"#
+        );
+        assert_eq!(changes.len(), 2);
+        assert_eq!(rewrite_cargo(&out, &Proxy::Github).0, manifest);
+
+        let lock = r#"[[package]]
+name = "foo"
+version = "0.1.0"
+source = "git+https://github.com/owner/foo?branch=main#0123456789abcdef"
+"#;
+        let (out, _) = rewrite_cargo(lock, &Proxy::Xget);
+        assert!(out.contains(
+            r#"source = "git+https://xget.xi-xu.me/gh/owner/foo?branch=main#0123456789abcdef""#
+        ));
+        assert_eq!(rewrite_cargo(&out, &Proxy::Github).0, lock);
+
+        // Blob links are retargeted to the raw file, like in every other rewriter
+        let (out, _) = rewrite_cargo(
+            "git = \"https://github.com/owner/foo/blob/main/Cargo.toml\"\n",
+            &Proxy::Xget,
+        );
+        assert_eq!(
+            out,
+            "git = \"https://xget.xi-xu.me/gh/owner/foo/raw/main/Cargo.toml\"\n"
+        );
+    }
+
+    #[test]
+    fn test_cargo_source_config() {
+        let lock = r#"[[package]]
+name = "foo"
+source = "git+https://github.com/owner/foo?branch=main#0123456789abcdef"
+
+[[package]]
+name = "foo-macros"
+source = "git+https://github.com/owner/foo?branch=main#0123456789abcdef"
+
+[[package]]
+name = "serde"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            cargo_source_config(lock, &Proxy::GhProxy).unwrap(),
+            r#"[source."git+https://github.com/owner/foo?branch=main"]
+git = "https://github.com/owner/foo"
+branch = "main"
+replace-with = "gh-proxy-owner-foo"
+
+[source."gh-proxy-owner-foo"]
+git = "https://gh-proxy.com/https://github.com/owner/foo"
+branch = "main"
+
+"#
+        );
+        assert!(cargo_source_config(lock, &Proxy::Jsdelivr).is_none());
+
+        // Names stay unique even when a repository is named like a suffixed one
+        let lock = r#"source = "git+https://github.com/owner/foo?branch=a#0"
+source = "git+https://github.com/owner/foo-2?branch=b#0"
+source = "git+https://github.com/owner/foo?branch=c#0"
+source = "git+https://github.com/owner/foo?tag=d#0"
+"#;
+        let config = cargo_source_config(lock, &Proxy::Xget).unwrap();
+        let names: Vec<&str> = config
+            .lines()
+            .filter_map(|line| line.strip_prefix("replace-with = "))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "\"xget-owner-foo\"",
+                "\"xget-owner-foo-2\"",
+                "\"xget-owner-foo-3\"",
+                "\"xget-owner-foo-4\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_proxy_chain() {
+        let chain = ProxyChain::from_str("xget, jsdelivr,github")
+            .unwrap()
+            .with_origin(Proxy::Xget, "http://127.0.0.1:8080/");
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+        assert_eq!(
+            chain.urls(&resource),
+            vec![
+                (
+                    Proxy::Xget,
+                    "http://127.0.0.1:8080/gh/owner/repo/releases/download/v1.0.0/app.tar.gz"
+                        .to_string()
+                ),
+                (
+                    Proxy::Github,
+                    "https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz".to_string()
+                ),
+            ]
+        );
+        assert!(ProxyChain::from_str("xget,invalid").is_err());
+    }
+
+    /// Serve raw HTTP responses built by `handler` from the request head
+    #[cfg(feature = "download")]
+    fn serve<F>(handler: F) -> String
+    where
+        F: Fn(&str) -> Vec<u8> + Send + 'static,
+    {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut head = String::new();
+                let mut reader = BufReader::new(&stream);
+                while reader.read_line(&mut head).is_ok_and(|n| n > 2) {}
+                let _ = stream.write_all(&handler(&head));
+            }
+        });
+        origin
+    }
+
+    #[cfg(feature = "download")]
+    fn response(status: &str, body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[cfg(feature = "download")]
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("github-proxy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_falls_back() {
+        let broken = serve(|_| response("404 Not Found", "missing"));
+        let working = serve(|head| {
+            assert!(head.starts_with("GET /gh/owner/repo/raw/main/install.sh "));
+            response("200 OK", "echo hello")
+        });
+        let chain = ProxyChain::from_str("gh-proxy,xget")
+            .unwrap()
+            .with_origin(Proxy::GhProxy, broken)
+            .with_origin(Proxy::Xget, working);
+        let resource = Resource::file(
+            "owner".to_string(),
+            "repo".to_string(),
+            "main".to_string(),
+            "install.sh".to_string(),
+        );
+        let path = temp_path("install.sh");
+        assert_eq!(fetch(&resource, &chain, &path).unwrap(), Proxy::Xget);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo hello");
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_times_out() {
+        // Accepts connections but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stalled = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let streams: Vec<_> = listener.incoming().collect();
+            drop(streams);
+        });
+        let working = serve(|_| response("200 OK", "echo hello"));
+        let chain = ProxyChain::from_str("gh-proxy,xget")
+            .unwrap()
+            .with_origin(Proxy::GhProxy, stalled)
+            .with_origin(Proxy::Xget, working)
+            .with_timeout(Duration::from_millis(200));
+        let resource = Resource::file("o".into(), "r".into(), "main".into(), "install.sh".into());
+        let path = temp_path("timeout.sh");
+        assert_eq!(fetch(&resource, &chain, &path).unwrap(), Proxy::Xget);
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_all_failed() {
+        let broken = serve(|_| response("500 Internal Server Error", ""));
+        let chain =
+            ProxyChain::new(vec![Proxy::Xget, Proxy::Jsdelivr]).with_origin(Proxy::Xget, broken);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+        let path = temp_path("app.tar.gz");
+        match fetch(&resource, &chain, &path) {
+            Err(ConversionError::DownloadFailed(attempts)) => {
+                assert_eq!(attempts.len(), 1);
+                assert_eq!(attempts[0].0, Proxy::Xget);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_race_keeps_fastest() {
+        let slow = serve(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            response("200 OK", "slow")
+        });
+        let broken = serve(|_| response("404 Not Found", ""));
+        let fast = serve(|_| response("200 OK", "fast"));
+        let chain = ProxyChain::from_str("github,gh-proxy,xget")
+            .unwrap()
+            .with_origin(Proxy::Github, slow)
+            .with_origin(Proxy::GhProxy, broken)
+            .with_origin(Proxy::Xget, fast);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "race.tar.gz".to_string(),
+        );
+        let path = temp_path("race.tar.gz");
+        assert_eq!(race(&resource, &chain, &path).unwrap(), Proxy::Xget);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fast");
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_race_falls_back_to_runner_up() {
+        // Answers first but breaks off the body
+        let truncated = serve(|_| {
+            b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\nConnection: close\r\n\r\npart".to_vec()
+        });
+        // Answers first with an LFS pointer instead of the content
+        let pointer = serve(|_| {
+            response(
+                "200 OK",
+                "version https://git-lfs.github.com/spec/v1\noid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\nsize 4\n",
+            )
+        });
+        let slow = serve(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            response("200 OK", "full")
+        });
+        let chain = ProxyChain::from_str("github,xget,jsdelivr")
+            .unwrap()
+            .with_origin(Proxy::Github, slow)
+            .with_origin(Proxy::Xget, truncated)
+            .with_origin(Proxy::Jsdelivr, pointer);
+        let resource = Resource::file("o".into(), "r".into(), "main".into(), "model.bin".into());
+        let path = temp_path("runner-up.bin");
+        assert_eq!(race(&resource, &chain, &path).unwrap(), Proxy::Github);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "full");
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_probe_ranking() {
+        let slow = serve(|head| {
+            assert!(head.contains("Range: bytes=0-65535"));
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            response("206 Partial Content", "#!/bin/sh")
+        });
+        let fast = serve(|head| {
+            assert!(head.starts_with("GET /gh/owner/repo/raw/main/install.sh "));
+            response("206 Partial Content", "#!/bin/sh")
+        });
+        let broken = serve(|_| response("403 Forbidden", ""));
+        let chain = ProxyChain::from_str("github,xget,gh-proxy")
+            .unwrap()
+            .with_origin(Proxy::Github, slow)
+            .with_origin(Proxy::Xget, fast)
+            .with_origin(Proxy::GhProxy, broken);
+        let resource = Resource::file(
+            "owner".to_string(),
+            "repo".to_string(),
+            "main".to_string(),
+            "install.sh".to_string(),
+        );
+
+        let probes = probe(&resource, &chain);
+        assert_eq!(
+            probes.iter().map(|p| p.proxy).collect::<Vec<_>>(),
+            vec![Proxy::Xget, Proxy::Github, Proxy::GhProxy]
+        );
+        assert_eq!(probes[0].status, Some(206));
+        assert_eq!(probes[2].status, Some(403));
+        assert!(!probes[2].is_ok());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+        let all: Vec<Proxy> = <Proxy as strum::IntoEnumIterator>::iter().collect();
+        let mut ranking = Ranking::default();
+        // Without data the order is kept, minus unsupported proxies
+        assert_eq!(
+            ranking.rank_at(&resource, &all, 1000),
+            vec![Proxy::Github, Proxy::GhProxy, Proxy::Xget]
+        );
+
+        ranking.record_success_at(Proxy::Xget, Some(Duration::from_millis(80)), 1000);
+        ranking.record_success_at(Proxy::GhProxy, Some(Duration::from_millis(40)), 1000);
+        ranking.record_failure_at(Proxy::GhProxy, 1010);
+        assert_eq!(
+            ranking.rank_at(&resource, &all, 1020),
+            vec![Proxy::Xget, Proxy::Github, Proxy::GhProxy]
+        );
+
+        // The cooldown expires, then the measured latencies go stale
+        let later = 1010 + FAILURE_COOLDOWN.as_secs();
+        assert_eq!(
+            ranking.rank_at(&resource, &all, later),
+            vec![Proxy::GhProxy, Proxy::Xget, Proxy::Github]
+        );
+        let stale = 1000 + RANKING_TTL.as_secs();
+        assert_eq!(
+            ranking.rank_at(&resource, &all, stale),
+            vec![Proxy::Github, Proxy::GhProxy, Proxy::Xget]
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("github-proxy-ranking-{}", std::process::id()));
+        ranking.save_to(&path).unwrap();
+        assert_eq!(Ranking::load_from(&path).unwrap(), ranking);
+        std::fs::remove_file(path).unwrap();
+
+        // Only proxies that can render the resource are picked
+        let best = Proxy::best_for(&resource).unwrap();
+        assert!(resource.url(&best).is_some());
+        let image = Resource::image("ghcr.io".into(), "owner/app".into(), "v1".into());
+        assert_eq!(Proxy::best_for(&image), None);
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_resumes_on_next_proxy() {
+        let body = "0123456789".repeat(100);
+        let first = body[..500].to_string();
+        let rest = body[500..].to_string();
+        // Drops the connection halfway through the body
+        let dropping = serve(move |_| {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 1000\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n{}",
+                first
+            )
+            .into_bytes()
+        });
+        let resuming = serve(move |head| {
+            assert!(head.contains("Range: bytes=500-"));
+            format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 500-999/1000\r\nContent-Length: 500\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n{}",
+                rest
+            )
+            .into_bytes()
+        });
+        let chain = ProxyChain::from_str("xget,gh-proxy")
+            .unwrap()
+            .with_origin(Proxy::Xget, dropping)
+            .with_origin(Proxy::GhProxy, resuming);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "resume.tar.gz".to_string(),
+        );
+        let path = temp_path("resume.tar.gz");
+        assert_eq!(fetch(&resource, &chain, &path).unwrap(), Proxy::GhProxy);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), body);
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_restarts_on_different_content() {
+        let dropping = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"old\"\r\nConnection: close\r\n\r\nstale"
+                .as_bytes()
+                .to_vec()
+        });
+        let changed = serve(|head| {
+            if head.contains("Range: bytes=5-") {
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\nContent-Length: 5\r\nETag: \"new\"\r\nConnection: close\r\n\r\nWRONG"
+                    .as_bytes()
+                    .to_vec()
+            } else {
+                response("200 OK", "fresh body")
+            }
+        });
+        let chain = ProxyChain::from_str("xget,gh-proxy")
+            .unwrap()
+            .with_origin(Proxy::Xget, dropping)
+            .with_origin(Proxy::GhProxy, changed);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "restart.tar.gz".to_string(),
+        );
+        let path = temp_path("restart.tar.gz");
+        assert_eq!(fetch(&resource, &chain, &path).unwrap(), Proxy::GhProxy);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fresh body");
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_fetch_ignores_stale_partial_file() {
+        let server = serve(|head| {
+            if head.contains("Range: bytes=") {
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\nContent-Length: 5\r\nETag: \"v2\"\r\nConnection: close\r\n\r\nWRONG"
+                    .as_bytes()
+                    .to_vec()
+            } else {
+                response("200 OK", "fresh body")
+            }
+        });
+        let chain = ProxyChain::from_str("xget")
+            .unwrap()
+            .with_origin(Proxy::Xget, server);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "stale.tar.gz".to_string(),
+        );
+        let path = temp_path("stale.tar.gz");
+        let part = temp_path("stale.tar.gz.part");
+        let id = temp_path("stale.tar.gz.part.id");
+
+        // Left behind by an earlier run without a recorded identity
+        std::fs::write(&part, "stale").unwrap();
+        let _ = std::fs::remove_file(&id);
+        fetch(&resource, &chain, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fresh body");
+
+        // Recorded for a different version of the file
+        std::fs::write(&part, "stale").unwrap();
+        std::fs::write(&id, "10\n\"v1\"\n").unwrap();
+        fetch(&resource, &chain, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fresh body");
+        assert!(!part.exists() && !id.exists());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_parse_checksums() {
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let sums = format!(
+            "{}  other.tar.gz\n{} *app.tar.gz\n",
+            "0".repeat(64),
+            hash.to_uppercase()
+        );
+        assert_eq!(
+            checksum::parse_checksums(&sums, "app.tar.gz").unwrap(),
+            hash
+        );
+        let bsd = format!("SHA256 (app.tar.gz) = {}\n", hash);
+        assert_eq!(checksum::parse_checksums(&bsd, "app.tar.gz").unwrap(), hash);
+        assert_eq!(
+            checksum::parse_checksums(&format!("{}\n", hash), "app.tar.gz").unwrap(),
+            hash
+        );
+        assert!(checksum::parse_checksums(&sums, "missing.zip").is_none());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_discover_and_verify_sha256() {
+        // sha256("test")
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let server = serve(move |head| {
+            if head.starts_with("GET /gh/owner/repo/releases/download/v1.0.0/SHA256SUMS ") {
+                response("200 OK", &format!("{}  app.tar.gz\n", hash))
+            } else if head.starts_with("GET /gh/owner/repo/releases/download/v1.0.0/app.tar.gz ") {
+                response("200 OK", "test")
+            } else {
+                response("404 Not Found", "")
+            }
+        });
+        let chain = ProxyChain::new(vec![Proxy::Xget]).with_origin(Proxy::Xget, server);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+        let path = temp_path("verify.tar.gz");
+        let proxy = fetch(&resource, &chain, &path).unwrap();
+        let expected = discover_sha256(&resource, &proxy, &chain).unwrap();
+        assert_eq!(expected, hash);
+        verify_sha256(&path, &expected).unwrap();
+        assert!(matches!(
+            verify_sha256(&path, &"0".repeat(64)),
+            Err(ConversionError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_verify_without_checksum_asset() {
+        let server = serve(|head| {
+            if head.starts_with("GET /gh/owner/repo/releases/download/v1.0.0/app.tar.gz ") {
+                response("200 OK", "test")
+            } else {
+                response("404 Not Found", "")
+            }
+        });
+        let chain = ProxyChain::new(vec![Proxy::Xget]).with_origin(Proxy::Xget, server);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+        let path = temp_path("unverified.tar.gz");
+        let proxy = fetch(&resource, &chain, &path).unwrap();
+        let path = path.to_str().unwrap();
+        assert!(matches!(
+            cli::verify(path, &resource, &proxy, &chain, None, true, None),
+            Err(ConversionError::ChecksumNotFound(_))
+        ));
+        assert!(!std::path::Path::new(path).exists());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_verify_evicts_cached_download() {
+        let server = serve(|_| response("200 OK", "corrupt"));
+        let chain = ProxyChain::new(vec![Proxy::Xget]).with_origin(Proxy::Xget, server);
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "cached.tar.gz".to_string(),
+        );
+        let dir = temp_path("verify-cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = Cache::new(&dir);
+        let cached = cache.fetch(&resource, &chain).unwrap();
+        let path = temp_path("cached.tar.gz");
+        std::fs::copy(&cached.path, &path).unwrap();
+
+        // sha256("test")
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let path = path.to_str().unwrap();
+        assert!(matches!(
+            cli::verify(
+                path,
+                &resource,
+                &Proxy::Xget,
+                &chain,
+                Some(hash.into()),
+                false,
+                Some(&cache)
+            ),
+            Err(ConversionError::ChecksumMismatch { .. })
+        ));
+        assert!(cache.entries().unwrap().is_empty());
+        assert!(!cached.path.exists());
+        // The next fetch asks upstream again
+        assert_eq!(
+            cache.fetch(&resource, &chain).unwrap().proxy,
+            Some(Proxy::Xget)
+        );
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_audit() {
+        let genuine = || serve(|_| response("200 OK", "genuine"));
+        let tampered = serve(|_| response("200 OK", "tampered"));
+        let broken = serve(|_| response("502 Bad Gateway", ""));
+        let resource = Resource::release(
+            "owner".to_string(),
+            "repo".to_string(),
+            "v1.0.0".to_string(),
+            "app.tar.gz".to_string(),
+        );
+
+        let chain = ProxyChain::from_str("xget,gh-proxy,github")
+            .unwrap()
+            .with_origin(Proxy::Xget, genuine())
+            .with_origin(Proxy::GhProxy, broken)
+            .with_origin(Proxy::Github, genuine());
+        let report = audit(&resource, &chain).unwrap();
+        assert_eq!(report.reference, Proxy::Github);
+        assert_eq!(report.digests.len(), 2);
+        assert_eq!(report.failures[0].0, Proxy::GhProxy);
+
+        let chain = ProxyChain::from_str("xget,gh-proxy,github")
+            .unwrap()
+            .with_origin(Proxy::Xget, tampered)
+            .with_origin(Proxy::GhProxy, genuine())
+            .with_origin(Proxy::Github, genuine());
+        match audit(&resource, &chain) {
+            Err(ConversionError::ContentMismatch {
+                proxy,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(proxy, Proxy::Xget);
+                assert_eq!(expected, sha256_reader("genuine".as_bytes()).unwrap());
+                assert_eq!(actual, sha256_reader("tampered".as_bytes()).unwrap());
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_redirect() {
+        let reply = redirect("/owner/repo/raw/main/install.sh", None, &Proxy::Xget);
+        assert_eq!(reply.status, 302);
+        assert_eq!(
+            reply.location.unwrap(),
+            "https://xget.xi-xu.me/gh/owner/repo/raw/main/install.sh"
+        );
+
+        // The query takes precedence over the header
+        let reply = redirect(
+            "/owner/repo/releases/download/v1.0.0/app.tar.gz?proxy=gh-proxy",
+            Some("xget"),
+            &Proxy::Github,
+        );
+        assert_eq!(
+            reply.location.unwrap(),
+            "https://gh-proxy.com/https://github.com/owner/repo/releases/download/v1.0.0/app.tar.gz"
+        );
+        let reply = redirect(
+            "/owner/repo/releases/download/v1.0.0/app.tar.gz",
+            Some("xget"),
+            &Proxy::Github,
+        );
+        assert!(
+            reply
+                .location
+                .unwrap()
+                .starts_with("https://xget.xi-xu.me/")
+        );
+
+        assert_eq!(
+            redirect("/owner/repo/issues/1", None, &Proxy::Xget).status,
+            404
+        );
+        assert_eq!(
+            redirect("/owner/repo/raw/main/a.sh?proxy=nope", None, &Proxy::Xget).status,
+            400
+        );
+        assert_eq!(
+            redirect(
+                "/owner/repo/releases/download/v1/a.zip",
+                None,
+                &Proxy::Jsdelivr
+            )
+            .status,
+            400
+        );
+    }
+
+    #[cfg(all(feature = "server", feature = "download"))]
+    #[test]
+    fn test_stream() {
+        let upstream = serve(|head| {
+            // Without a Content-Length the body ends with the connection
+            if head.contains("/owner/repo/raw/main/unsized.bin") {
+                return b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nabcdef".to_vec();
+            }
+            if !head.contains("/owner/repo/raw/main/data.bin") {
+                return response("404 Not Found", "");
+            }
+            if head.contains("Range: bytes=2-") {
+                return b"HTTP/1.1 206 Partial Content\r\nContent-Length: 4\r\n\
+                    Content-Range: bytes 2-5/6\r\nETag: \"abc\"\r\nX-Secret: 1\r\n\
+                    Connection: close\r\n\r\ncdef"
+                    .to_vec();
+            }
+            response("200 OK", "abcdef")
+        });
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let config = ServerConfig {
+            stream: true,
+            chain: ProxyChain::new(Vec::new()).with_origin(Proxy::Github, upstream),
+            max_size: Some(5),
+            ..ServerConfig::new(Proxy::Github)
+        };
+        std::thread::spawn(move || server::run(server, config));
+
+        let reply = ureq::get(&format!("{}/owner/repo/raw/main/data.bin", addr))
+            .set("Range", "bytes=2-")
+            .call()
+            .unwrap();
+        assert_eq!(reply.status(), 206);
+        assert_eq!(reply.header("Content-Range"), Some("bytes 2-5/6"));
+        assert_eq!(reply.header("ETag"), Some("\"abc\""));
+        assert_eq!(reply.header("X-Secret"), None);
+        assert_eq!(reply.into_string().unwrap(), "cdef");
+
+        // The whole file exceeds the size limit, and a body of unknown length
+        // can't be checked against it
+        for (path, status) in [("data.bin", 413), ("unsized.bin", 502)] {
+            match ureq::get(&format!("{}/owner/repo/raw/main/{}", addr, path)).call() {
+                Err(ureq::Error::Status(code, _)) if code == status => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        match ureq::get(&format!("{}/owner/repo/raw/main/missing.bin", addr)).call() {
+            Err(ureq::Error::Status(404, _)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(all(feature = "server", feature = "download"))]
+    #[test]
+    fn test_stream_from_cache() {
+        let upstream = serve(|head| {
+            if head.contains("/owner/repo/raw/main/data.txt") {
+                return b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nContent-Type: text/plain\r\n\
+                    ETag: \"abc\"\r\nConnection: close\r\n\r\nabc"
+                    .to_vec();
+            }
+            if head.contains("/owner/repo/raw/main/large.bin") {
+                return response("200 OK", "abcdef");
+            }
+            if head.contains("/owner/repo/raw/main/unsized.bin") {
+                return b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nabcdef".to_vec();
+            }
+            response("404 Not Found", "")
+        });
+        let dir = temp_path("stream-cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let config = ServerConfig {
+            stream: true,
+            chain: ProxyChain::new(Vec::new()).with_origin(Proxy::Github, upstream),
+            max_size: Some(5),
+            cache: Some(Cache::new(&dir)),
+            ..ServerConfig::new(Proxy::Github)
+        };
+        std::thread::spawn(move || server::run(server, config));
+        let get = |path: &str| match ureq::get(&format!("{}/owner/repo/raw/main/{}", addr, path))
+            .call()
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => panic!("request failed: {}", e),
+        };
+
+        for _ in 0..2 {
+            let reply = get("data.txt");
+            assert_eq!(reply.status(), 200);
+            assert_eq!(reply.header("Content-Type"), Some("text/plain"));
+            assert_eq!(reply.header("ETag"), Some("\"abc\""));
+            assert_eq!(reply.into_string().unwrap(), "abc");
+        }
+        assert_eq!(get("missing.txt").status(), 404);
+        assert_eq!(get("large.bin").status(), 413);
+        assert_eq!(get("unsized.bin").status(), 413);
+        // Only the small body was stored
+        let blobs = std::fs::read_dir(dir.join("blobs")).unwrap().count();
+        assert_eq!(blobs, 1);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_policy() {
+        let release = |owner: &str, repo: &str| {
+            Resource::release(owner.into(), repo.into(), "v1".into(), "app.zip".into())
+        };
+        let file = Resource::file("my-org".into(), "tool".into(), "main".into(), "a.sh".into());
+        assert!(Policy::default().check(&file).is_ok());
+
+        let policy = Policy {
+            allow_owners: vec!["my-org".to_string()],
+            allow_repos: vec!["friend/lib".to_string()],
+            deny_repos: vec!["my-org/secret".to_string()],
+            allow_kinds: vec!["release".to_string()],
+            ..Policy::default()
+        };
+        assert!(policy.check(&release("my-org", "tool")).is_ok());
+        assert!(policy.check(&release("My-Org", "tool")).is_ok());
+        assert!(policy.check(&release("friend", "lib")).is_ok());
+        assert!(policy.check(&release("friend", "other")).is_err());
+        assert!(policy.check(&release("my-org", "secret")).is_err());
+        assert_eq!(
+            policy.check(&file),
+            Err("file resources are not allowed".to_string())
+        );
+
+        // Encoded names are matched by what they decode to
+        assert!(policy.check(&release("my%2Dorg", "tool")).is_ok());
+        assert!(policy.check(&release("my-org", "s%65cret")).is_err());
+        assert!(policy.check(&release("my-org", "bad%zz")).is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_rate_limit() {
+        let limiter = policy::Limiter::default();
+        let limit = RateLimit {
+            requests: 2,
+            window: Duration::from_secs(60),
+        };
+        let start = std::time::Instant::now();
+        let client = std::net::IpAddr::from([127, 0, 0, 1]);
+        let other = std::net::IpAddr::from([127, 0, 0, 2]);
+
+        assert!(limiter.check(&limit, client, start).is_ok());
+        assert!(limiter.check(&limit, client, start).is_ok());
+        assert_eq!(
+            limiter.check(&limit, client, start + Duration::from_secs(20)),
+            Err(Duration::from_secs(40))
+        );
+        assert!(limiter.check(&limit, other, start).is_ok());
+        assert!(
+            limiter
+                .check(&limit, client, start + Duration::from_secs(60))
+                .is_ok()
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_server_dot_segments() {
+        use std::io::{Read, Write};
+
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let mut config = ServerConfig::new(Proxy::Xget);
+        config.policy = Policy {
+            allow_owners: vec!["my-org".to_string()],
+            ..Policy::default()
+        };
+        std::thread::spawn(move || server::run(server, config));
+        // Sent as is, HTTP clients would resolve the segments themselves
+        let status = |path: &str| {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "GET {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+                path
+            )
+            .unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).unwrap();
+            reply[9..12].to_string()
+        };
+
+        assert_eq!(status("/my-org/repo/releases/download/v1/app.zip"), "302");
+        assert_eq!(
+            status(
+                "/my-org/repo/releases/download/v1/../../../../../evil/repo/releases/download/v1/app.zip"
+            ),
+            "400"
+        );
+        assert_eq!(
+            status("/my-org/repo/raw/main/%2e%2e/%2E%2E/%2e%2e/%2e%2e/evil/repo/raw/main/a.sh"),
+            "400"
+        );
+        assert_eq!(status("/my-org/repo/raw/main/.%2F..%2Fa.sh"), "400");
+        assert_eq!(status("/evil/repo/raw/main/a.sh"), "403");
+
+        let file =
+            |path: &str| Resource::file("my-org".into(), "repo".into(), "main".into(), path.into());
+        let policy = Policy::default();
+        assert!(
+            policy
+                .check(&file("../../evil/repo/raw/main/a.sh"))
+                .is_err()
+        );
+        assert!(policy.check(&file("docs/%2e%2e/a.sh")).is_err());
+        assert!(policy.check(&file("docs/..a.sh")).is_ok());
+    }
+
+    #[cfg(all(feature = "server", feature = "download"))]
+    #[test]
+    fn test_server_policy() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let mut config = ServerConfig::new(Proxy::Xget);
+        config.policy = Policy {
+            deny_owners: vec!["evil".to_string()],
+            rate_limit: Some(RateLimit {
+                requests: 2,
+                window: Duration::from_secs(60),
+            }),
+            ..Policy::default()
+        };
+        std::thread::spawn(move || server::run(server, config));
+        let agent = ureq::AgentBuilder::new().redirects(0).build();
+        let get = |path: &str| match agent.get(&format!("{}{}", addr, path)).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => panic!("request failed: {}", e),
+        };
+
+        assert_eq!(get("/owner/repo/raw/main/a.sh").status(), 302);
+        let denied = get("/%65vil/repo/raw/main/a.sh");
+        assert_eq!(denied.status(), 403);
+        assert_eq!(denied.into_string().unwrap(), "Owner evil is not allowed");
+        let limited = get("/owner/repo/raw/main/a.sh");
+        assert_eq!(limited.status(), 429);
+        assert!(limited.header("Retry-After").is_some());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_cache() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let origin = serve(move |head| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if head.contains("If-None-Match: \"v1\"") {
+                return b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_vec();
+            }
+            b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nETag: \"v1\"\r\n\
+                Connection: close\r\n\r\ncontent"
+                .to_vec()
+        });
+        let chain = ProxyChain::new(vec![Proxy::Github]).with_origin(Proxy::Github, origin);
+        let dir = temp_path("cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = Cache::new(&dir);
+
+        // Release assets are never revalidated
+        let release = Resource::release("o".into(), "r".into(), "v1".into(), "app.zip".into());
+        let first = cache.fetch(&release, &chain).unwrap();
+        assert_eq!(first.proxy, Some(Proxy::Github));
+        assert_eq!(std::fs::read_to_string(&first.path).unwrap(), "content");
+        let second = cache.fetch(&release, &chain).unwrap();
+        assert_eq!(second.proxy, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Branch files are revalidated and share the blob of identical content
+        let file = Resource::file("o".into(), "r".into(), "main".into(), "a.txt".into());
+        assert_eq!(cache.fetch(&file, &chain).unwrap().path, first.path);
+        let revalidated = cache.fetch(&file, &chain).unwrap();
+        assert_eq!(revalidated.proxy, Some(Proxy::Github));
+        assert_eq!(revalidated.path, first.path);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Assets of moving tags are kept for a while
+        let nightly = Resource::release("o".into(), "r".into(), "nightly".into(), "app.zip".into());
+        cache.fetch(&nightly, &chain).unwrap();
+        assert_eq!(cache.fetch(&nightly, &chain).unwrap().proxy, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(
+            entries
+                .iter()
+                .any(|e| !e.immutable && e.fresh_until.is_some())
+        );
+        assert!(
+            entries
+                .iter()
+                .any(|e| e.immutable && e.url.contains("/releases/"))
+        );
+
+        // Identical content counts once towards the size
+        assert!(cache.prune(7).unwrap().is_empty());
+
+        std::fs::write(&first.path, "tampered").unwrap();
+        assert_eq!(cache.verify().unwrap().len(), 3);
+        assert!(cache.entries().unwrap().is_empty());
+        assert!(!first.path.exists());
+
+        cache.fetch(&release, &chain).unwrap();
+        assert_eq!(cache.prune(0).unwrap().len(), 1);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reference_kind() {
+        assert_eq!(ReferenceKind::of("a1b2c3d"), ReferenceKind::CommitSha);
+        assert_eq!(
+            ReferenceKind::of("0123456789abcdef0123456789abcdef01234567"),
+            ReferenceKind::CommitSha
+        );
+        assert_eq!(ReferenceKind::of("a1b2c3"), ReferenceKind::Unknown);
+        // Abbreviated hashes can be made of digits only
+        assert_eq!(ReferenceKind::of("4821736"), ReferenceKind::CommitSha);
+        assert_eq!(ReferenceKind::of("20240101"), ReferenceKind::CommitSha);
+        assert_eq!(ReferenceKind::of("v20240101"), ReferenceKind::Tag);
+        assert_eq!(ReferenceKind::of("2024.01.01"), ReferenceKind::Tag);
+        assert_eq!(ReferenceKind::of("v2"), ReferenceKind::Tag);
+        assert_eq!(ReferenceKind::of("2"), ReferenceKind::Unknown);
+        assert_eq!(ReferenceKind::of("refs/tags/v1.0"), ReferenceKind::Tag);
+        assert_eq!(ReferenceKind::of("v1.2.3"), ReferenceKind::Tag);
+        assert_eq!(ReferenceKind::of("refs/heads/main"), ReferenceKind::Branch);
+        assert_eq!(
+            ReferenceKind::of("refs/pull/1/head"),
+            ReferenceKind::FullRef
+        );
+        assert_eq!(ReferenceKind::of("main"), ReferenceKind::Unknown);
+        assert_eq!(ReferenceKind::of("release-1"), ReferenceKind::Unknown);
+
+        let file = |reference: &str| {
+            Resource::file("o".into(), "r".into(), reference.into(), "a.txt".into())
+        };
+        assert!(file("0123456789abcdef0123456789abcdef01234567").is_immutable());
+        assert!(!file("a1b2c3d").is_immutable());
+        assert!(!file("deadbeef").is_immutable());
+        assert!(!file("20240101").is_immutable());
+        assert!(!file("main").is_immutable());
+        assert!(!file("refs/tags/v1.0").is_immutable());
+        let release = |tag: &str| Resource::release("o".into(), "r".into(), tag.into(), "a".into());
+        assert_eq!(
+            release("nightly").reference_kind(),
+            Some(ReferenceKind::Tag)
+        );
+        assert!(release("v1.2.3").is_immutable());
+        assert!(release("2.0.0-rc.1").is_immutable());
+        assert!(!release("nightly").is_immutable());
+        let repository = Resource::repository("o".into(), "r".into());
+        assert_eq!(repository.reference_kind(), None);
+        assert!(!repository.is_immutable());
+    }
+
+    #[test]
+    fn test_jsdelivr() {
+        let file = |reference: &str, path: &str| {
+            Resource::file("o".into(), "r".into(), reference.into(), path.into())
+        };
+
+        // Version ranges only resolve on jsdelivr
+        assert_eq!(ReferenceKind::of("^1"), ReferenceKind::VersionRange);
+        assert_eq!(ReferenceKind::of("1.x"), ReferenceKind::VersionRange);
+        assert_eq!(ReferenceKind::of("latest"), ReferenceKind::VersionRange);
+        assert_eq!(ReferenceKind::of("1.2"), ReferenceKind::Tag);
+        let range = file("^1.2", "dist/app.js");
+        assert_eq!(
+            range.url(&Proxy::Jsdelivr).unwrap(),
+            "https://cdn.jsdelivr.net/gh/o/r@^1.2/dist/app.js"
+        );
+        assert!(!range.is_immutable());
+
+        // Elsewhere they are ordinary branch and tag names
+        for reference in ["1.x", "latest"] {
+            assert_eq!(
+                file(reference, "install.sh").url(&Proxy::Github).unwrap(),
+                format!("https://github.com/o/r/raw/{}/install.sh", reference)
+            );
+            assert_eq!(
+                file(reference, "install.sh").url(&Proxy::Xget).unwrap(),
+                format!("https://xget.xi-xu.me/gh/o/r/raw/{}/install.sh", reference)
+            );
+            let archive = Resource::try_from(
+                format!("https://github.com/o/r/archive/{}.tar.gz", reference).as_str(),
+            )
+            .unwrap();
+            assert_eq!(
+                archive.url(&Proxy::Xget).unwrap(),
+                format!("https://xget.xi-xu.me/gh/o/r/archive/{}.tar.gz", reference)
+            );
+        }
+
+        assert_eq!(
+            file("main", "dist/app.js").minified().unwrap(),
+            file("main", "dist/app.min.js")
+        );
+        assert_eq!(file("main", "dist/app.min.css").minified(), None);
+        assert_eq!(file("main", "README.md").minified(), None);
+
+        assert_eq!(
+            file("v1.0.0", "dist/app.js").purge_url().unwrap(),
+            "https://purge.jsdelivr.net/gh/o/r@v1.0.0/dist/app.js"
+        );
+        let release = Resource::release("o".into(), "r".into(), "v1".into(), "a.zip".into());
+        assert_eq!(release.purge_url(), None);
+
+        // Directory listings
+        let (proxy, tree) =
+            Resource::from_proxied_url("https://cdn.jsdelivr.net/gh/o/r@main/dist/").unwrap();
+        assert_eq!(proxy, Proxy::Jsdelivr);
+        assert_eq!(
+            tree,
+            Resource::tree("o".into(), "r".into(), "main".into(), "dist".into())
+        );
+        assert_eq!(
+            tree.url(&Proxy::Jsdelivr).unwrap(),
+            "https://cdn.jsdelivr.net/gh/o/r@main/dist/"
+        );
+        assert_eq!(tree.url(&Proxy::Statically), None);
+        assert_eq!(
+            tree.purge_url().unwrap(),
+            "https://purge.jsdelivr.net/gh/o/r@main/dist/"
+        );
+        let (_, root) =
+            Resource::from_proxied_url("https://cdn.jsdelivr.net/gh/o/r@refs/heads/dev/").unwrap();
+        assert_eq!(
+            root,
+            Resource::tree("o".into(), "r".into(), "refs/heads/dev".into(), "".into())
+        );
+        assert_eq!(
+            root.url(&Proxy::Jsdelivr).unwrap(),
+            "https://cdn.jsdelivr.net/gh/o/r@refs/heads/dev/"
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = Resource::try_from("https://github.com/o/r/tree/main/src/bin").unwrap();
+        assert_eq!(
+            tree,
+            Resource::tree("o".into(), "r".into(), "main".into(), "src/bin".into())
+        );
+        assert_eq!(
+            tree.url(&Proxy::Github).unwrap(),
+            "https://api.github.com/repos/o/r/contents/src/bin?ref=main"
+        );
+        assert_eq!(
+            tree.url(&Proxy::Jsdelivr).unwrap(),
+            "https://cdn.jsdelivr.net/gh/o/r@main/src/bin/"
+        );
+        assert_eq!(
+            tree.url(&Proxy::GhProxy).unwrap(),
+            "https://gh-proxy.com/https://api.github.com/repos/o/r/contents/src/bin?ref=main"
+        );
+        assert_eq!(
+            tree.url(&Proxy::Xget).unwrap(),
+            "https://xget.xi-xu.me/api/github/repos/o/r/contents/src/bin?ref=main"
+        );
+
+        let root = Resource::try_from("https://github.com/o/r/tree/v1.0/").unwrap();
+        assert_eq!(
+            root,
+            Resource::tree("o".into(), "r".into(), "v1.0".into(), "".into())
+        );
+        assert_eq!(
+            root.url(&Proxy::Github).unwrap(),
+            "https://api.github.com/repos/o/r/contents?ref=v1.0"
+        );
+        assert_eq!(
+            Resource::try_from("https://github.com/o/r/tree/refs/heads/dev/docs").unwrap(),
+            Resource::tree(
+                "o".into(),
+                "r".into(),
+                "refs/heads/dev".into(),
+                "docs".into()
+            )
+        );
+
+        let error = tree.try_url(&Proxy::Statically).unwrap_err();
+        assert!(matches!(
+            error,
+            ConversionError::UnsupportedResource {
+                proxy: Proxy::Statically,
+                kind: "tree"
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "statically proxy does not support tree resources"
+        );
+
+        // Directory links are meant for people
+        let text = "See https://github.com/o/r/tree/main/src";
+        assert_eq!(rewrite_text(text, &Proxy::Jsdelivr), text);
+    }
+
+    #[test]
+    fn test_patch() {
+        let commit =
+            Resource::try_from("https://github.com/o/r/commit/0123abc4567def.patch").unwrap();
+        assert_eq!(
+            commit,
+            Resource::commit(
+                "o".into(),
+                "r".into(),
+                "0123abc4567def".into(),
+                PatchFormat::Patch
+            )
+        );
+        // Abbreviated hashes can become ambiguous
+        assert!(!commit.is_immutable());
+        assert_eq!(
+            commit.url(&Proxy::Xget).unwrap(),
+            "https://xget.xi-xu.me/gh/o/r/commit/0123abc4567def.patch"
+        );
+        assert_eq!(commit.url(&Proxy::Jsdelivr), None);
+
+        let pull = Resource::try_from("https://github.com/o/r/pull/42.diff").unwrap();
+        assert_eq!(
+            pull,
+            Resource::pull_request("o".into(), "r".into(), 42, PatchFormat::Diff)
+        );
+        assert!(!pull.is_immutable());
+        assert_eq!(
+            pull.url(&Proxy::GhProxy).unwrap(),
+            "https://gh-proxy.com/https://github.com/o/r/pull/42.diff"
+        );
+
+        let compare =
+            Resource::try_from("https://github.com/o/r/compare/v1.0.0...feature/x.patch").unwrap();
+        assert_eq!(
+            compare,
+            Resource::compare(
+                "o".into(),
+                "r".into(),
+                "v1.0.0".into(),
+                "feature/x".into(),
+                PatchFormat::Patch
+            )
+        );
+        assert_eq!(
+            compare.url(&Proxy::Github).unwrap(),
+            "https://github.com/o/r/compare/v1.0.0...feature/x.patch"
+        );
+
+        // Proxied patch URLs round trip
+        let (proxy, parsed) =
+            Resource::from_proxied_url(&compare.url(&Proxy::Xget).unwrap()).unwrap();
+        assert_eq!((proxy, parsed), (Proxy::Xget, compare));
+
+        assert!(Resource::try_from("https://github.com/o/r/pull/42").is_err());
+        assert_eq!(
+            rewrite_text(
+                "curl -L https://github.com/o/r/pull/42.patch | git am",
+                &Proxy::Xget
+            ),
+            "curl -L https://xget.xi-xu.me/gh/o/r/pull/42.patch | git am"
+        );
+    }
+
+    #[test]
+    fn test_api() {
+        let api = |endpoint| Resource::api("o".into(), "r".into(), endpoint);
+        let parse = |url: &str| Resource::try_from(url).unwrap();
+
+        let latest = parse("https://api.github.com/repos/o/r/releases/latest");
+        assert_eq!(latest, api(ApiEndpoint::LatestRelease));
+        assert_eq!(
+            latest.url(&Proxy::GhProxy).unwrap(),
+            "https://gh-proxy.com/https://api.github.com/repos/o/r/releases/latest"
+        );
+        assert_eq!(
+            latest.url(&Proxy::Xget).unwrap(),
+            "https://xget.xi-xu.me/api/github/repos/o/r/releases/latest"
+        );
+        assert_eq!(latest.url(&Proxy::Jsdelivr), None);
+        assert!(!latest.is_immutable());
+
+        assert_eq!(
+            parse("https://api.github.com/repos/o/r/releases/tags/v1.0"),
+            api(ApiEndpoint::Release { tag: "v1.0".into() })
+        );
+        let contents = parse("https://api.github.com/repos/o/r/contents/src/lib.rs?ref=dev");
+        assert_eq!(
+            contents,
+            api(ApiEndpoint::Contents {
+                path: "src/lib.rs".into(),
+                reference: Some("dev".into())
+            })
+        );
+        assert_eq!(
+            contents.url(&Proxy::Github).unwrap(),
+            "https://api.github.com/repos/o/r/contents/src/lib.rs?ref=dev"
+        );
+        assert_eq!(
+            parse("https://api.github.com/repos/o/r/contents"),
+            api(ApiEndpoint::Contents {
+                path: "".into(),
+                reference: None
+            })
+        );
+        let tree = parse("https://api.github.com/repos/o/r/git/trees/main?recursive=1");
+        assert_eq!(
+            tree,
+            api(ApiEndpoint::GitTree {
+                reference: "main".into(),
+                recursive: true
+            })
+        );
+        assert_eq!(
+            tree.url(&Proxy::Github).unwrap(),
+            "https://api.github.com/repos/o/r/git/trees/main?recursive=1"
+        );
+
+        // Every endpoint parses back from the URL each proxy renders
+        let endpoints = [
+            ApiEndpoint::LatestRelease,
+            ApiEndpoint::Release { tag: "v1.0".into() },
+            ApiEndpoint::Contents {
+                path: "src/lib.rs".into(),
+                reference: Some("dev".into()),
+            },
+            ApiEndpoint::Contents {
+                path: "src".into(),
+                reference: None,
+            },
+            ApiEndpoint::GitTree {
+                reference: "main".into(),
+                recursive: true,
+            },
+            ApiEndpoint::GitTree {
+                reference: "main".into(),
+                recursive: false,
+            },
+        ];
+        for endpoint in endpoints {
+            let resource = api(endpoint);
+            for proxy in [Proxy::Github, Proxy::GhProxy, Proxy::Xget] {
+                let url = resource.url(&proxy).unwrap();
+                assert_eq!(
+                    Resource::from_proxied_url(&url).unwrap(),
+                    (proxy, resource.clone())
+                );
+            }
+        }
+
+        assert!(Resource::try_from("https://api.github.com/repos/o/r/issues").is_err());
+        assert_eq!(
+            rewrite_text(
+                "curl -s https://api.github.com/repos/o/r/releases/latest | jq .tag_name",
+                &Proxy::GhProxy
+            ),
+            "curl -s https://gh-proxy.com/https://api.github.com/repos/o/r/releases/latest | jq .tag_name"
+        );
+    }
+
+    #[test]
+    fn test_select_asset() {
+        let names = [
+            "ei-aarch64-apple-darwin.tar.gz",
+            "ei-x86_64-apple-darwin.tar.gz",
+            "ei-x86_64-unknown-linux-gnu.tar.gz",
+            "ei-x86_64-unknown-linux-musl.tar.gz",
+            "ei-x86_64-unknown-linux-musl.tar.gz.sha256",
+            "ei-i686-unknown-linux-musl.tar.gz",
+            "ei-x86_64-pc-windows-msvc.zip",
+            "ei-x86_64-pc-windows-msvc.msi",
+            "checksums.txt",
+        ];
+        let select = |selector: &str| {
+            let selector = AssetSelector::from_str(selector).unwrap();
+            match select_asset("easy-install", "easy-install", "nightly", &names, &selector) {
+                Some(Resource::Release { name, .. }) => Some(name),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            select("aarch64-apple-darwin").unwrap(),
+            "ei-aarch64-apple-darwin.tar.gz"
+        );
+        assert_eq!(
+            select("darwin-arm64").unwrap(),
+            "ei-aarch64-apple-darwin.tar.gz"
+        );
+        assert_eq!(
+            select("x86_64-unknown-linux-musl").unwrap(),
+            "ei-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            select("x86_64-unknown-linux-gnu").unwrap(),
+            "ei-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        // Without an ABI the static musl build is preferred
+        assert_eq!(
+            select("linux-amd64").unwrap(),
+            "ei-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            select("linux/386").unwrap(),
+            "ei-i686-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            select("windows-x64").unwrap(),
+            "ei-x86_64-pc-windows-msvc.zip"
+        );
+        assert_eq!(select("linux-arm64"), None);
+        assert_eq!(select("*.msi").unwrap(), "ei-x86_64-pc-windows-msvc.msi");
+        assert_eq!(
+            select("ei-*-linux-?nu.tar.gz").unwrap(),
+            "ei-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        assert_eq!(select("*.deb"), None);
+
+        let fish = [
+            "fish-4.1.2-linux-aarch64.tar.xz",
+            "fish-4.1.2-linux-x86_64.tar.xz",
+            "fish-4.1.2.tar.xz",
+        ];
+        let target = AssetSelector::from_str("aarch64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            select_asset("fish-shell", "fish-shell", "4.1.2", &fish, &target).unwrap(),
+            Resource::release(
+                "fish-shell".into(),
+                "fish-shell".into(),
+                "4.1.2".into(),
+                "fish-4.1.2-linux-aarch64.tar.xz".into()
+            )
+        );
+        // The source tarball names no platform and fits none
+        for target in ["aarch64-apple-darwin", "windows-x64"] {
+            let target = AssetSelector::from_str(target).unwrap();
+            assert_eq!(
+                select_asset("fish-shell", "fish-shell", "4.1.2", &fish, &target),
+                None
+            );
+        }
+
+        assert_eq!(
+            Target::from_str("x86_64-unknown-linux-musl").unwrap(),
+            Target {
+                os: Os::Linux,
+                arch: Arch::X86_64,
+                env: Some("musl".into())
+            }
+        );
+        assert!(Target::from_str("plan9").is_err());
+    }
+
+    #[test]
+    fn test_lfs() {
+        let pointer = "version https://git-lfs.github.com/spec/v1\noid sha256:4D7A214614AB2935C943F9E0FF69D22EADBB8F32B1258DAAA5E2CA24D17E2393\nsize 12345\n";
+        assert_eq!(
+            LfsPointer::parse(pointer.as_bytes()),
+            Some(LfsPointer {
+                oid: "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393".to_string(),
+                size: 12345,
+            })
+        );
+        assert_eq!(LfsPointer::parse(b"version 1\nsize 3\n"), None);
+        assert_eq!(
+            LfsPointer::parse(
+                b"version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 3\n"
+            ),
+            None
+        );
+
+        let url = "https://media.githubusercontent.com/media/o/r/main/assets/model.bin";
+        let file = Resource::try_from(url).unwrap();
+        assert_eq!(
+            file,
+            Resource::file(
+                "o".into(),
+                "r".into(),
+                "main".into(),
+                "assets/model.bin".into()
+            )
+        );
+        assert_eq!(file.media_url().unwrap(), url);
+        assert!(Proxy::Xget.resolves_lfs());
+        assert!(!Proxy::Jsdelivr.resolves_lfs());
+        // CDNs would serve the pointer, so media URLs are kept for them
+        assert_eq!(rewrite_text(url, &Proxy::Jsdelivr), url);
+        assert_eq!(
+            rewrite_text(url, &Proxy::Xget),
+            "https://xget.xi-xu.me/gh/o/r/raw/main/assets/model.bin"
+        );
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_lfs_pointer_falls_back() {
+        const POINTER: &str = "version https://git-lfs.github.com/spec/v1\noid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\nsize 5\n";
+        let cdn = serve(|_| response("200 OK", POINTER));
+        let github = serve(|_| response("200 OK", "model"));
+        let chain = ProxyChain::from_str("jsdelivr,github")
+            .unwrap()
+            .with_origin(Proxy::Jsdelivr, cdn)
+            .with_origin(Proxy::Github, github);
+        let resource = Resource::file("o".into(), "r".into(), "main".into(), "model.bin".into());
+
+        let path = temp_path("model.bin");
+        assert_eq!(fetch(&resource, &chain, &path).unwrap(), Proxy::Github);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "model");
+
+        let cache = Cache::new(temp_path("lfs-cache"));
+        let cached = cache.fetch(&resource, &chain).unwrap();
+        assert_eq!(cached.proxy, Some(Proxy::Github));
+        assert_eq!(std::fs::read_to_string(&cached.path).unwrap(), "model");
+
+        // With only CDNs the pointer is reported rather than saved
+        let only_cdn = ProxyChain::new(vec![Proxy::Jsdelivr])
+            .with_origin(Proxy::Jsdelivr, serve(|_| response("200 OK", POINTER)));
+        let err = fetch(&resource, &only_cdn, temp_path("pointer.bin")).unwrap_err();
+        assert!(err.to_string().contains("Git LFS pointer"));
+        assert!(!temp_path("pointer.bin").exists());
+    }
+
+    #[test]
+    fn test_image() {
+        let image = Resource::try_from("ghcr.io/my-org/tools/app:1.2.0").unwrap();
+        assert_eq!(
+            image,
+            Resource::image("ghcr.io".into(), "my-org/tools/app".into(), "1.2.0".into())
+        );
+        assert_eq!((image.owner(), image.repo()), ("my-org", "tools/app"));
+        assert!(!image.is_immutable());
+        assert_eq!(
+            image.pull_reference(&Proxy::Xget).unwrap(),
+            "xget.xi-xu.me/cr/ghcr/my-org/tools/app:1.2.0"
+        );
+        assert_eq!(
+            image.pull_reference(&Proxy::Github).unwrap(),
+            "ghcr.io/my-org/tools/app:1.2.0"
+        );
+        assert!(image.pull_reference(&Proxy::Jsdelivr).is_none());
+        // A pull reference is no URL to download
+        assert_eq!(image.url(&Proxy::Github), None);
+        assert_eq!(
+            image.mirrored_image("https://mirror.example.com/").unwrap(),
+            "mirror.example.com/my-org/tools/app:1.2.0"
+        );
+        assert_eq!(
+            Resource::from_proxied_url("xget.xi-xu.me/cr/ghcr/my-org/tools/app:1.2.0").unwrap(),
+            (Proxy::Xget, image)
+        );
+
+        // Without a tag the image is latest, a digest wins over a tag
+        let digest = format!("sha256:{}", "a".repeat(64));
+        assert_eq!(
+            Resource::try_from("ghcr.io/o/app").unwrap().reference(),
+            Some("latest")
+        );
+        let pinned = Resource::try_from(format!("ghcr.io/o/app:1.0@{}", digest).as_str()).unwrap();
+        assert_eq!(pinned.reference(), Some(digest.as_str()));
+        assert!(pinned.is_immutable());
+        assert_eq!(
+            pinned.pull_reference(&Proxy::Github).unwrap(),
+            format!("ghcr.io/o/app@{}", digest)
+        );
+        assert!(Resource::try_from("ghcr.io/app").is_err());
+        assert!(Resource::try_from("ghcr.io/My-Org/app").is_err());
+
+        let mirror = registry_mirror(&Proxy::Xget, "ghcr.io").unwrap();
+        assert_eq!(
+            containerd_hosts_snippet("ghcr.io", &mirror),
+            "server = \"https://ghcr.io\"\n\n[host.\"https://xget.xi-xu.me/v2/cr/ghcr\"]\n  capabilities = [\"pull\", \"resolve\"]\n  override_path = true\n"
+        );
+        assert!(
+            !containerd_hosts_snippet("ghcr.io", "https://mirror.example.com")
+                .contains("override_path")
+        );
+        assert_eq!(registry_mirror(&Proxy::GhProxy, "ghcr.io"), None);
+        assert_eq!(
+            daemon_json_snippet("https://mirror.example.com").unwrap(),
+            "{\n  \"registry-mirrors\": [\"https://mirror.example.com\"]\n}\n"
+        );
+        assert_eq!(daemon_json_snippet(&mirror), None);
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_image_not_downloadable() {
+        let image = Resource::try_from("ghcr.io/o/app").unwrap();
+        let chain = ProxyChain::new(vec![Proxy::Github, Proxy::Xget]);
+        let path = temp_path("app-image");
+        assert!(matches!(
+            fetch(&image, &chain, &path),
+            Err(ConversionError::ImageNotDownloadable(reference)) if reference == "ghcr.io/o/app:latest"
+        ));
+        assert!(matches!(
+            race(&image, &chain, &path),
+            Err(ConversionError::ImageNotDownloadable(_))
+        ));
+        assert!(matches!(
+            Cache::new(temp_path("image-cache")).fetch(&image, &chain),
+            Err(ConversionError::ImageNotDownloadable(_))
+        ));
+    }
+
+    #[cfg(feature = "resolve")]
+    #[test]
+    fn test_resolve_release() {
+        let api = serve(|head| {
+            let body = if head.starts_with("GET /https://api.github.com/repos/o/r/releases/latest ")
+            {
+                r#"{"tag_name":"v2.0.0","assets":[{"name":"ei-x86_64-unknown-linux-musl.tar.gz"},{"name":"ei-x86_64-pc-windows-msvc.zip"},{"name":"checksums.txt"}]}"#
+            } else if head
+                .starts_with("GET /https://api.github.com/repos/o/r/releases/tags/v1.0.0 ")
+            {
+                r#"{"tag_name":"v1.0.0","assets":[{"name":"ei-x86_64-pc-windows-msvc.zip"}]}"#
+            } else {
+                return response("404 Not Found", "{}");
+            };
+            response("200 OK", body)
+        });
+        let chain = ProxyChain::new(vec![Proxy::GhProxy]).with_origin(Proxy::GhProxy, api);
+
+        let info = release_info("o", "r", None, &chain).unwrap();
+        assert_eq!(info.tag, "v2.0.0");
+        assert_eq!(info.assets.len(), 3);
+
+        let latest = |name: &str| {
+            resolve_release(
+                &Resource::release("o".into(), "r".into(), "latest".into(), name.into()),
+                &chain,
+            )
+        };
+        assert_eq!(
+            latest("*windows*").unwrap(),
+            Resource::release(
+                "o".into(),
+                "r".into(),
+                "v2.0.0".into(),
+                "ei-x86_64-pc-windows-msvc.zip".into()
+            )
+        );
+        assert_eq!(
+            latest("linux-amd64").unwrap(),
+            Resource::release(
+                "o".into(),
+                "r".into(),
+                "v2.0.0".into(),
+                "ei-x86_64-unknown-linux-musl.tar.gz".into()
+            )
+        );
+        assert!(matches!(
+            latest("*.dmg"),
+            Err(ConversionError::AssetNotFound { .. })
+        ));
+        // A name missing from the release is not trusted
+        assert!(latest("app.tar.gz").is_err());
+
+        let tagged = resolve_release(
+            &Resource::release("o".into(), "r".into(), "v1.0.0".into(), "*.zip".into()),
+            &chain,
+        )
+        .unwrap();
+        assert_eq!(tagged.reference(), Some("v1.0.0"));
+        // Target selectors are resolved for concrete tags too
+        let tagged = resolve_release(
+            &Resource::release(
+                "o".into(),
+                "r".into(),
+                "v1.0.0".into(),
+                "windows-x64".into(),
+            ),
+            &chain,
+        )
+        .unwrap();
+        assert_eq!(
+            tagged,
+            Resource::release(
+                "o".into(),
+                "r".into(),
+                "v1.0.0".into(),
+                "ei-x86_64-pc-windows-msvc.zip".into()
+            )
+        );
+
+        // Concrete releases need no request
+        let concrete = Resource::release("x".into(), "y".into(), "v1".into(), "a.zip".into());
+        assert_eq!(resolve_release(&concrete, &chain).unwrap(), concrete);
+
+        // Without the API the latest tag comes from the redirect
+        let github = serve(|head| {
+            if head.starts_with("GET /o/r/releases/latest ") {
+                b"HTTP/1.1 302 Found\r\nLocation: https://github.com/o/r/releases/tag/v3.1.0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+            } else {
+                response("404 Not Found", "")
+            }
+        });
+        let chain = ProxyChain::new(vec![Proxy::Github]).with_origin(Proxy::Github, github);
+        let resolved = resolve_release(
+            &Resource::release("o".into(), "r".into(), "latest".into(), "app.tar.gz".into()),
+            &chain,
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            Resource::release("o".into(), "r".into(), "v3.1.0".into(), "app.tar.gz".into())
+        );
+        // Selectors can't be resolved without the asset list
+        let selector = Resource::release(
+            "o".into(),
+            "r".into(),
+            "latest".into(),
+            "linux-amd64".into(),
+        );
+        assert!(matches!(
+            resolve_release(&selector, &chain),
+            Err(ConversionError::AssetNotFound { .. })
+        ));
+    }
 }
@@ -0,0 +1,138 @@
+use crate::{Proxy, Resource};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Which resources the server is willing to serve
+///
+/// Empty allow lists allow everything, deny lists win over allow lists.
+/// Owners and repositories are compared case-insensitively after
+/// percent-decoding, as GitHub does.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Policy {
+    /// Owners whose resources are served, e.g. `my-org`
+    pub allow_owners: Vec<String>,
+    pub deny_owners: Vec<String>,
+    /// Repositories whose resources are served, as `owner/repo`
+    pub allow_repos: Vec<String>,
+    pub deny_repos: Vec<String>,
+    /// Resource kinds that are served, see [`Resource::KINDS`]
+    pub allow_kinds: Vec<String>,
+    /// Requests allowed per client address
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// At most `requests` requests per `window`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub window: Duration,
+}
+
+impl Policy {
+    /// Check whether `resource` may be served, returning the reason if not
+    ///
+    /// Resources whose path, reference or asset name contains `.` or `..`
+    /// segments are always refused, since upstream would resolve them to
+    /// another repository.
+    pub fn check(&self, resource: &Resource) -> Result<(), String> {
+        if resource
+            .url(&Proxy::Github)
+            .is_some_and(|url| has_dot_segment(url.split('?').next().unwrap_or_default()))
+        {
+            return Err("Paths with . or .. segments are not allowed".to_string());
+        }
+        let (Some(owner), Some(repo)) = (
+            percent_decode(resource.owner()),
+            percent_decode(resource.repo()),
+        ) else {
+            return Err("Invalid percent-encoding in repository".to_string());
+        };
+        let full = format!("{}/{}", owner, repo);
+        let listed =
+            |list: &[String], name: &str| list.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        if listed(&self.deny_owners, &owner) {
+            return Err(format!("Owner {} is not allowed", owner));
+        }
+        if listed(&self.deny_repos, &full) {
+            return Err(format!("Repository {} is not allowed", full));
+        }
+        let restricted = !self.allow_owners.is_empty() || !self.allow_repos.is_empty();
+        if restricted && !listed(&self.allow_owners, &owner) && !listed(&self.allow_repos, &full) {
+            return Err(format!("Repository {} is not allowed", full));
+        }
+        if !self.allow_kinds.is_empty() && !listed(&self.allow_kinds, resource.kind()) {
+            return Err(format!("{} resources are not allowed", resource.kind()));
+        }
+        Ok(())
+    }
+}
+
+/// Fixed window request counter per client address
+#[derive(Debug, Default)]
+pub(crate) struct Limiter {
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl Limiter {
+    /// Count a request from `client`, returning how long to wait if over the limit
+    pub(crate) fn check(
+        &self,
+        limit: &RateLimit,
+        client: IpAddr,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget clients whose window has passed so the map can't grow without bound
+        if clients.len() > 10_000 {
+            clients.retain(|_, (start, _)| now.duration_since(*start) < limit.window);
+        }
+
+        let (start, count) = clients.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= limit.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= limit.requests {
+            return Err(limit.window - now.duration_since(*start));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Whether a URL path has a `.` or `..` segment, also when percent-encoded
+///
+/// Malformed escapes count as such a segment, as they can't be checked.
+pub(crate) fn has_dot_segment(path: &str) -> bool {
+    path.split('/')
+        .any(|segment| match percent_decode(segment) {
+            Some(decoded) => decoded
+                .split(['/', '\\'])
+                .any(|part| part == "." || part == ".."),
+            None => true,
+        })
+}
+
+/// Decode `%XX` escapes, None if they are malformed or not UTF-8
+pub(crate) fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
@@ -0,0 +1,116 @@
+use crate::download::{agent, describe};
+use crate::{Proxy, ProxyChain, Resource};
+use std::io::Read as _;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Number of bytes requested from each proxy when probing
+pub const PROBE_BYTES: u64 = 64 * 1024;
+
+/// Measurement of a single proxy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub proxy: Proxy,
+    /// HTTP status, None when no response was received
+    pub status: Option<u16>,
+    /// Time until the response headers arrived
+    pub latency: Duration,
+    /// Bytes per second while reading the sampled body
+    pub throughput: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl Probe {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Probe every proxy of the chain with a small range request for `resource`
+///
+/// Proxies are probed concurrently. The result is ranked with working
+/// proxies first, ordered by latency, followed by the failed ones.
+pub fn probe(resource: &Resource, chain: &ProxyChain) -> Vec<Probe> {
+    let agent = agent(chain);
+    let (tx, rx) = mpsc::channel();
+    let mut probes = Vec::new();
+
+    for proxy in chain.proxies() {
+        let Some(url) = chain.url(proxy, resource) else {
+            probes.push(Probe {
+                proxy: *proxy,
+                status: None,
+                latency: Duration::ZERO,
+                throughput: None,
+                error: Some("resource not supported".to_string()),
+            });
+            continue;
+        };
+        let agent = agent.clone();
+        let tx = tx.clone();
+        let proxy = *proxy;
+        std::thread::spawn(move || {
+            let _ = tx.send(probe_url(&agent, proxy, &url));
+        });
+    }
+    drop(tx);
+
+    probes.extend(rx);
+    rank(&mut probes);
+    probes
+}
+
+/// Sort probes: working proxies by latency first, failures last
+fn rank(probes: &mut [Probe]) {
+    probes.sort_by(|a, b| b.is_ok().cmp(&a.is_ok()).then(a.latency.cmp(&b.latency)));
+}
+
+fn probe_url(agent: &ureq::Agent, proxy: Proxy, url: &str) -> Probe {
+    let start = Instant::now();
+    let result = agent
+        .get(url)
+        .set("Range", &format!("bytes=0-{}", PROBE_BYTES - 1))
+        .call();
+    let latency = start.elapsed();
+
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            let status = match &e {
+                ureq::Error::Status(code, _) => Some(*code),
+                ureq::Error::Transport(_) => None,
+            };
+            return Probe {
+                proxy,
+                status,
+                latency,
+                throughput: None,
+                error: Some(describe(e)),
+            };
+        }
+    };
+
+    let status = Some(response.status());
+    let start = Instant::now();
+    // Servers ignoring Range send the whole file, only sample the beginning
+    let mut reader = response.into_reader().take(PROBE_BYTES);
+    let result = std::io::copy(&mut reader, &mut std::io::sink());
+    let elapsed = start.elapsed().as_secs_f64();
+
+    match result {
+        Ok(bytes) => Probe {
+            proxy,
+            status,
+            latency,
+            throughput: (elapsed > 0.0).then(|| bytes as f64 / elapsed),
+            error: None,
+        },
+        Err(e) => Probe {
+            proxy,
+            status,
+            latency,
+            throughput: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
@@ -1,5 +1,6 @@
-use crate::{Resource, error::ConversionError};
-use std::{fmt, str::FromStr};
+use crate::{Resource, error::ConversionError, ranking::Ranking};
+use std::{collections::HashMap, fmt, str::FromStr, time::Duration};
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 /// Proxy service types
@@ -50,4 +51,127 @@ impl Proxy {
     pub fn url(&self, resource: Resource) -> Option<String> {
         resource.url(self)
     }
+
+    /// Scheme and host every URL of this proxy starts with
+    pub fn origin(&self) -> &'static str {
+        match self {
+            Proxy::Github => "https://github.com",
+            Proxy::GhProxy => "https://gh-proxy.com",
+            Proxy::Xget => "https://xget.xi-xu.me",
+            Proxy::Jsdelivr => "https://cdn.jsdelivr.net",
+            Proxy::Statically => "https://cdn.statically.io",
+        }
+    }
+
+    /// Best proxy for the resource according to the persisted [`Ranking`]
+    ///
+    /// Proxies that failed recently are avoided. Without any recorded data
+    /// the first proxy supporting the resource is returned. Returns None if
+    /// no proxy can render the resource, e.g. for container images.
+    pub fn best_for(resource: &Resource) -> Option<Proxy> {
+        let candidates: Vec<Proxy> = Proxy::iter()
+            .filter(|proxy| resource.url(proxy).is_some())
+            .collect();
+        Ranking::load().best_for(resource, &candidates)
+    }
+
+    /// Whether files tracked by Git LFS are served with their content
+    ///
+    /// CDNs serving straight from the git tree return the LFS pointer
+    /// instead, see [`crate::LfsPointer`].
+    pub fn resolves_lfs(&self) -> bool {
+        match self {
+            Proxy::Github | Proxy::GhProxy | Proxy::Xget => true,
+            Proxy::Jsdelivr | Proxy::Statically => false,
+        }
+    }
+
+    /// Prefix that replaces `https://github.com/` when cloning through this proxy
+    ///
+    /// Returns None for CDNs that only serve files and can't be used with git
+    pub fn clone_prefix(&self) -> Option<&'static str> {
+        match self {
+            Proxy::Github => Some("https://github.com/"),
+            Proxy::GhProxy => Some("https://gh-proxy.com/https://github.com/"),
+            Proxy::Xget => Some("https://xget.xi-xu.me/gh/"),
+            Proxy::Jsdelivr | Proxy::Statically => None,
+        }
+    }
+}
+
+/// Ordered list of proxies to try for a resource
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProxyChain {
+    proxies: Vec<Proxy>,
+    origins: HashMap<Proxy, String>,
+    timeout: Option<Duration>,
+}
+
+impl ProxyChain {
+    pub fn new(proxies: Vec<Proxy>) -> Self {
+        ProxyChain {
+            proxies,
+            origins: HashMap::new(),
+            timeout: None,
+        }
+    }
+
+    /// Serve `proxy` from another origin, e.g. a self-hosted instance
+    ///
+    /// URLs keep the proxy's path layout, only the scheme and host change.
+    pub fn with_origin(mut self, proxy: Proxy, origin: impl Into<String>) -> Self {
+        self.origins
+            .insert(proxy, origin.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Give up on a proxy when connecting or reading stalls for this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxies(&self) -> &[Proxy] {
+        &self.proxies
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// URL of the resource through `proxy`, honoring origin overrides
+    pub fn url(&self, proxy: &Proxy, resource: &Resource) -> Option<String> {
+        let url = resource.url(proxy)?;
+        match (self.origins.get(proxy), url.strip_prefix(proxy.origin())) {
+            (Some(origin), Some(rest)) => Some(format!("{}{}", origin, rest)),
+            _ => Some(url),
+        }
+    }
+
+    /// URLs of the resource through every proxy that supports it, in order
+    pub fn urls(&self, resource: &Resource) -> Vec<(Proxy, String)> {
+        self.proxies
+            .iter()
+            .filter_map(|proxy| Some((*proxy, self.url(proxy, resource)?)))
+            .collect()
+    }
+}
+
+impl Default for ProxyChain {
+    /// Every proxy, in declaration order
+    fn default() -> Self {
+        ProxyChain::new(Proxy::iter().collect())
+    }
+}
+
+impl FromStr for ProxyChain {
+    type Err = ConversionError;
+    /// Parse a comma separated list such as `xget,gh-proxy,github`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let proxies = s
+            .split(',')
+            .map(|p| Proxy::from_str(p.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ProxyChain::new(proxies))
+    }
 }
@@ -0,0 +1,185 @@
+use crate::{Proxy, Resource};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr as _;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a recorded latency is trusted
+pub const RANKING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a failing proxy is demoted
+pub const FAILURE_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+struct Entry {
+    /// Latency of the last successful request, if it was measured
+    latency: Option<Duration>,
+    /// Unix time of the last success
+    succeeded: Option<u64>,
+    /// Unix time until which the proxy is demoted after a failure
+    failed_until: Option<u64>,
+}
+
+/// Recorded successes and failures of each proxy, persisted in the user's cache dir
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Ranking {
+    entries: HashMap<Proxy, Entry>,
+}
+
+impl Ranking {
+    /// Default location: `<cache dir>/github-proxy/ranking`
+    pub fn path() -> Option<PathBuf> {
+        Some(cache_dir()?.join("github-proxy").join("ranking"))
+    }
+
+    /// Load the ranking from the default location, empty if there is none
+    pub fn load() -> Ranking {
+        Ranking::path()
+            .and_then(|path| Ranking::load_from(path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn load_from(path: impl AsRef<Path>) -> std::io::Result<Ranking> {
+        Ok(Ranking::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Save the ranking to the default location
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Ranking::path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no cache directory")
+        })?;
+        self.save_to(path)
+    }
+
+    pub fn save_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Record a successful request, clearing any cooldown
+    pub fn record_success(&mut self, proxy: Proxy, latency: Option<Duration>) {
+        self.record_success_at(proxy, latency, now());
+    }
+
+    /// Record a failed request, demoting the proxy for [`FAILURE_COOLDOWN`]
+    pub fn record_failure(&mut self, proxy: Proxy) {
+        self.record_failure_at(proxy, now());
+    }
+
+    /// Order the proxies that support `resource`, best first
+    ///
+    /// Proxies with a recent success come first, fastest first, then proxies
+    /// without recent data in their given order. Proxies in cooldown go last.
+    pub fn rank(&self, resource: &Resource, proxies: &[Proxy]) -> Vec<Proxy> {
+        self.rank_at(resource, proxies, now())
+    }
+
+    /// The best proxy for `resource` among `proxies`
+    pub fn best_for(&self, resource: &Resource, proxies: &[Proxy]) -> Option<Proxy> {
+        self.rank(resource, proxies).first().copied()
+    }
+
+    pub(crate) fn record_success_at(&mut self, proxy: Proxy, latency: Option<Duration>, now: u64) {
+        let entry = self.entries.entry(proxy).or_default();
+        entry.latency = latency.or(entry.latency);
+        entry.succeeded = Some(now);
+        entry.failed_until = None;
+    }
+
+    pub(crate) fn record_failure_at(&mut self, proxy: Proxy, now: u64) {
+        let entry = self.entries.entry(proxy).or_default();
+        entry.failed_until = Some(now + FAILURE_COOLDOWN.as_secs());
+    }
+
+    pub(crate) fn rank_at(&self, resource: &Resource, proxies: &[Proxy], now: u64) -> Vec<Proxy> {
+        let mut ranked: Vec<(usize, Proxy)> = proxies
+            .iter()
+            .filter(|proxy| resource.url(proxy).is_some())
+            .copied()
+            .enumerate()
+            .collect();
+
+        // (cooling down, no fresh success, latency, original position)
+        ranked.sort_by_key(|(i, proxy)| {
+            let entry = self.entries.get(proxy).copied().unwrap_or_default();
+            let cooling = entry.failed_until.is_some_and(|until| until > now);
+            let fresh = entry
+                .succeeded
+                .is_some_and(|at| now.saturating_sub(at) < RANKING_TTL.as_secs());
+            let latency = if fresh { entry.latency } else { None };
+            (cooling, !fresh, latency.unwrap_or(Duration::MAX), *i)
+        });
+
+        ranked.into_iter().map(|(_, proxy)| proxy).collect()
+    }
+
+    /// Parse lines of `<proxy> <latency ms|-> <succeeded|-> <failed until|->`
+    fn parse(text: &str) -> Ranking {
+        let mut entries = HashMap::new();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [proxy, latency, succeeded, failed_until] = fields[..] else {
+                continue;
+            };
+            let Ok(proxy) = Proxy::from_str(proxy) else {
+                continue;
+            };
+            entries.insert(
+                proxy,
+                Entry {
+                    latency: latency.parse().ok().map(Duration::from_millis),
+                    succeeded: succeeded.parse().ok(),
+                    failed_until: failed_until.parse().ok(),
+                },
+            );
+        }
+        Ranking { entries }
+    }
+}
+
+impl std::fmt::Display for Ranking {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut proxies: Vec<&Proxy> = self.entries.keys().collect();
+        proxies.sort_by_key(|proxy| proxy.to_string());
+        let field = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+
+        for proxy in proxies {
+            let entry = &self.entries[proxy];
+            writeln!(
+                f,
+                "{} {} {} {}",
+                proxy,
+                field(entry.latency.map(|l| l.as_millis() as u64)),
+                field(entry.succeeded),
+                field(entry.failed_until)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Platform cache directory, e.g. `~/.cache` on Linux
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let env = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+
+    if cfg!(windows) {
+        env("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        env("XDG_CACHE_HOME").or_else(|| env("HOME").map(|home| home.join(".cache")))
+    }
+}
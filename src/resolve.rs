@@ -0,0 +1,181 @@
+use crate::download::{agent, describe};
+use crate::{
+    ApiEndpoint, AssetSelector, ConversionError, ProxyChain, Resource, Target, select_asset,
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Tag and asset names of a published release
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReleaseInfo {
+    pub owner: String,
+    pub repo: String,
+    pub tag: String,
+    /// Empty when the tag was found through the `releases/latest` redirect
+    pub assets: Vec<String>,
+}
+
+impl ReleaseInfo {
+    /// The asset of this release that best fits `selector`, see [`select_asset`]
+    pub fn select(&self, selector: &AssetSelector) -> Option<Resource> {
+        select_asset(&self.owner, &self.repo, &self.tag, &self.assets, selector)
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiRelease {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<ApiAsset>,
+}
+
+#[derive(Deserialize)]
+struct ApiAsset {
+    name: String,
+}
+
+/// Look up a release, the latest one if `tag` is None
+///
+/// The releases API is queried through each proxy of the chain that
+/// supports it. If none answers, the latest tag is read from the redirect
+/// of `https://github.com/{owner}/{repo}/releases/latest`, without assets.
+pub fn release_info(
+    owner: &str,
+    repo: &str,
+    tag: Option<&str>,
+    chain: &ProxyChain,
+) -> Result<ReleaseInfo, ConversionError> {
+    let endpoint = match tag {
+        Some(tag) => ApiEndpoint::Release {
+            tag: tag.to_string(),
+        },
+        None => ApiEndpoint::LatestRelease,
+    };
+    let api = Resource::api(owner.to_string(), repo.to_string(), endpoint);
+    let agent = agent(chain);
+
+    let mut attempts = Vec::new();
+    for (proxy, url) in chain.urls(&api) {
+        let result = agent
+            .get(&url)
+            .set("Accept", "application/vnd.github+json")
+            .call()
+            .map_err(describe)
+            .and_then(|response| match response.status() {
+                200 => serde_json::from_reader::<_, ApiRelease>(response.into_reader())
+                    .map_err(|e| e.to_string()),
+                status => Err(format!("status {}", status)),
+            });
+        match result {
+            Ok(release) => {
+                return Ok(ReleaseInfo {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    tag: release.tag_name,
+                    assets: release.assets.into_iter().map(|asset| asset.name).collect(),
+                });
+            }
+            Err(e) => attempts.push((proxy, format!("{}: {}", url, e))),
+        }
+    }
+
+    if tag.is_none() {
+        let repository = Resource::repository(owner.to_string(), repo.to_string());
+        // The redirect itself names the tag, so don't follow it
+        let agent = without_redirects(chain);
+        for proxy in chain.proxies() {
+            let Some(base) = chain.url(proxy, &repository) else {
+                continue;
+            };
+            let url = format!("{}/releases/latest", base);
+            let result = agent
+                .get(&url)
+                .call()
+                .map_err(describe)
+                .and_then(|response| {
+                    response
+                        .header("Location")
+                        .and_then(|location| location.split_once("/releases/tag/"))
+                        .map(|(_, tag)| tag.trim_end_matches('/').to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .ok_or_else(|| {
+                            format!("status {} without a release tag", response.status())
+                        })
+                });
+            match result {
+                Ok(tag) => {
+                    return Ok(ReleaseInfo {
+                        owner: owner.to_string(),
+                        repo: repo.to_string(),
+                        tag,
+                        assets: Vec::new(),
+                    });
+                }
+                Err(e) => attempts.push((*proxy, format!("{}: {}", url, e))),
+            }
+        }
+    }
+
+    Err(ConversionError::DownloadFailed(attempts))
+}
+
+/// Fill in the tag and asset name of a release resource
+///
+/// A tag that is empty or `latest` is resolved to the latest release. The
+/// asset name is kept if the release has it, and otherwise read as an
+/// [`AssetSelector`] such as `*-linux-*.tar.gz` or `x86_64-unknown-linux-musl`.
+/// An empty name picks the asset for the running platform. A selector fails
+/// with [`ConversionError::AssetNotFound`] when the asset list is unknown.
+/// Releases with a concrete tag and file name are returned without a request,
+/// other resources as is.
+pub fn resolve_release(
+    resource: &Resource,
+    chain: &ProxyChain,
+) -> Result<Resource, ConversionError> {
+    let Resource::Release {
+        owner,
+        repo,
+        tag,
+        name,
+    } = resource
+    else {
+        return Ok(resource.clone());
+    };
+    let latest = tag.is_empty() || tag.eq_ignore_ascii_case("latest");
+    let selector = if name.is_empty() {
+        Target::current().map(AssetSelector::Target)
+    } else {
+        AssetSelector::from_str(name).ok()
+    };
+    if !latest && selector.is_none() && !name.is_empty() {
+        return Ok(resource.clone());
+    }
+
+    let info = release_info(owner, repo, (!latest).then_some(tag.as_str()), chain)?;
+    let concrete = !name.is_empty()
+        && (info.assets.contains(name) || (selector.is_none() && info.assets.is_empty()));
+    // Without an asset list only a file name can be trusted as is
+    if concrete {
+        return Ok(Resource::release(
+            owner.clone(),
+            repo.clone(),
+            info.tag,
+            name.clone(),
+        ));
+    }
+
+    selector
+        .and_then(|selector| info.select(&selector))
+        .ok_or_else(|| ConversionError::AssetNotFound {
+            tag: info.tag.clone(),
+            selector: name.clone(),
+        })
+}
+
+fn without_redirects(chain: &ProxyChain) -> ureq::Agent {
+    let mut builder = ureq::AgentBuilder::new().redirects(0);
+    if let Some(timeout) = chain.timeout() {
+        builder = builder.timeout_connect(timeout).timeout_read(timeout);
+    }
+    builder.build()
+}
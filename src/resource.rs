@@ -1,3 +1,4 @@
+use crate::image::{pull_prefix, xget_registry};
 use crate::proxy::Proxy;
 use strum_macros::EnumIter;
 
@@ -23,6 +24,251 @@ pub enum Resource {
         tag: String,
         name: String,
     },
+    /// Source archive of a reference
+    /// Format: owner/repo/reference.{zip,tar.gz}
+    Archive {
+        owner: String,
+        repo: String,
+        reference: String,
+        format: ArchiveFormat,
+    },
+    /// Git repository, as used for cloning
+    /// Format: owner/repo
+    Repository { owner: String, repo: String },
+    /// Directory in a repository, rendered as a listing: the GitHub API
+    /// contents endpoint or jsdelivr's directory listing
+    /// Format: owner/repo/reference/path, path is empty for the root
+    Tree {
+        owner: String,
+        repo: String,
+        reference: String,
+        path: String,
+    },
+    /// Patch or diff of a single commit
+    /// Format: owner/repo/commit/sha.{patch,diff}
+    Commit {
+        owner: String,
+        repo: String,
+        sha: String,
+        format: PatchFormat,
+    },
+    /// Patch or diff of a pull request
+    /// Format: owner/repo/pull/number.{patch,diff}
+    PullRequest {
+        owner: String,
+        repo: String,
+        number: u64,
+        format: PatchFormat,
+    },
+    /// Patch or diff between two references
+    /// Format: owner/repo/compare/base...head.{patch,diff}
+    Compare {
+        owner: String,
+        repo: String,
+        base: String,
+        head: String,
+        format: PatchFormat,
+    },
+    /// Read-only REST API endpoint of a repository
+    /// Format: api.github.com/repos/owner/repo/endpoint
+    Api {
+        owner: String,
+        repo: String,
+        endpoint: ApiEndpoint,
+    },
+    /// Container image, rendered as a reference to pull rather than a URL
+    /// Format: registry/name:tag or registry/name@sha256:digest
+    Image {
+        registry: String,
+        /// Repository in the registry, e.g. `owner/image`
+        name: String,
+        /// Tag, or digest such as `sha256:...`
+        reference: String,
+    },
+}
+
+/// REST API endpoints under `api.github.com/repos/{owner}/{repo}/`
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Default)]
+pub enum ApiEndpoint {
+    /// `releases/latest`
+    #[default]
+    LatestRelease,
+    /// `releases/tags/{tag}`
+    Release { tag: String },
+    /// `contents/{path}?ref={reference}`, a file or a directory listing
+    Contents {
+        path: String,
+        /// None for the default branch
+        reference: Option<String>,
+    },
+    /// `git/trees/{reference}`, with `?recursive=1` to include subdirectories
+    GitTree { reference: String, recursive: bool },
+}
+
+impl ApiEndpoint {
+    /// Path of the endpoint below the repository, including the query
+    pub fn path(&self) -> String {
+        match self {
+            ApiEndpoint::LatestRelease => "releases/latest".to_string(),
+            ApiEndpoint::Release { tag } => format!("releases/tags/{}", tag),
+            ApiEndpoint::Contents { path, reference } => {
+                let contents = format!("contents/{}", path)
+                    .trim_end_matches('/')
+                    .to_string();
+                match reference {
+                    Some(reference) => format!("{}?ref={}", contents, reference),
+                    None => contents,
+                }
+            }
+            ApiEndpoint::GitTree {
+                reference,
+                recursive,
+            } if *recursive => format!("git/trees/{}?recursive=1", reference),
+            ApiEndpoint::GitTree { reference, .. } => format!("git/trees/{}", reference),
+        }
+    }
+
+    /// Parse an endpoint path such as `releases/tags/v1.0`
+    fn parse(endpoint: &str) -> Option<ApiEndpoint> {
+        let (path, query) = endpoint.split_once('?').unwrap_or((endpoint, ""));
+        let param = |name: &str| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        };
+        let path = path.trim_end_matches('/');
+
+        if path == "releases/latest" {
+            return Some(ApiEndpoint::LatestRelease);
+        }
+        if let Some(tag) = path.strip_prefix("releases/tags/") {
+            return Some(ApiEndpoint::Release {
+                tag: tag.to_string(),
+            });
+        }
+        if path == "contents" || path.starts_with("contents/") {
+            return Some(ApiEndpoint::Contents {
+                path: path
+                    .trim_start_matches("contents")
+                    .trim_start_matches('/')
+                    .to_string(),
+                reference: param("ref").map(|r| r.to_string()),
+            });
+        }
+        if let Some(reference) = path.strip_prefix("git/trees/") {
+            return Some(ApiEndpoint::GitTree {
+                reference: reference.to_string(),
+                recursive: param("recursive").is_some_and(|v| v != "0" && v != "false"),
+            });
+        }
+        None
+    }
+}
+
+/// Source archive formats served from /archive/
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(EnumIter, Debug, PartialEq, Hash, Eq, Clone, Copy, Default)]
+pub enum ArchiveFormat {
+    #[default]
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// File extension used in archive URLs
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+/// Formats GitHub renders commits, pull requests and comparisons in
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(EnumIter, Debug, PartialEq, Hash, Eq, Clone, Copy, Default)]
+pub enum PatchFormat {
+    /// Mailbox format with commit messages, for `git am`
+    #[default]
+    Patch,
+    /// Plain unified diff, for `git apply` or `patch`
+    Diff,
+}
+
+impl PatchFormat {
+    /// File extension used in patch URLs
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Patch => "patch",
+            PatchFormat::Diff => "diff",
+        }
+    }
+}
+
+/// What a git reference string names
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Copy)]
+pub enum ReferenceKind {
+    /// Full or abbreviated commit hash, 7 to 40 hex digits
+    CommitSha,
+    /// `refs/tags/...`, or a bare version with a dot or a `v` prefix such as
+    /// `v1.2.3`, `1.2` or `v2`
+    Tag,
+    /// `refs/heads/...`
+    Branch,
+    /// Any other `refs/...` reference, e.g. `refs/pull/1/head`
+    FullRef,
+    /// Version range resolved by jsdelivr, e.g. `^1`, `~1.2`, `1.x` or `latest`,
+    /// and a plain branch or tag name everywhere else
+    VersionRange,
+    /// Bare name that could be a branch or a tag
+    Unknown,
+}
+
+impl ReferenceKind {
+    /// Classify a reference by its syntax alone, without asking GitHub
+    pub fn of(reference: &str) -> ReferenceKind {
+        // A dot or a `v` prefix tells versions apart from all-digit hashes
+        let is_version = |name: &str| {
+            let version = name.strip_prefix('v').unwrap_or(name);
+            !version.is_empty()
+                && (version.len() < name.len() || version.contains('.'))
+                && version
+                    .split('.')
+                    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        };
+
+        let is_range = |name: &str| {
+            name == "latest"
+                || name.starts_with(['^', '~', '<', '>', '='])
+                || name
+                    .split('.')
+                    .skip(1)
+                    .any(|part| matches!(part, "x" | "X" | "*"))
+        };
+
+        if reference.starts_with("refs/tags/") {
+            ReferenceKind::Tag
+        } else if reference.starts_with("refs/heads/") {
+            ReferenceKind::Branch
+        } else if reference.starts_with("refs/") {
+            ReferenceKind::FullRef
+        } else if is_version(reference) {
+            ReferenceKind::Tag
+        } else if (7..=40).contains(&reference.len())
+            && reference.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            ReferenceKind::CommitSha
+        } else if is_range(reference) {
+            ReferenceKind::VersionRange
+        } else {
+            ReferenceKind::Unknown
+        }
+    }
 }
 
 impl Resource {
@@ -52,10 +298,212 @@ impl Resource {
         }
     }
 
+    /// Create a new source archive resource
+    pub fn archive(owner: String, repo: String, reference: String, format: ArchiveFormat) -> Self {
+        Resource::Archive {
+            owner,
+            repo,
+            reference,
+            format,
+        }
+    }
+
+    /// Create a new repository resource
+    pub fn repository(owner: String, repo: String) -> Self {
+        Resource::Repository { owner, repo }
+    }
+
+    /// Create a new directory resource
+    pub fn tree(owner: String, repo: String, reference: String, path: String) -> Self {
+        Resource::Tree {
+            owner,
+            repo,
+            reference,
+            path,
+        }
+    }
+
+    /// Create a new commit patch resource
+    pub fn commit(owner: String, repo: String, sha: String, format: PatchFormat) -> Self {
+        Resource::Commit {
+            owner,
+            repo,
+            sha,
+            format,
+        }
+    }
+
+    /// Create a new pull request patch resource
+    pub fn pull_request(owner: String, repo: String, number: u64, format: PatchFormat) -> Self {
+        Resource::PullRequest {
+            owner,
+            repo,
+            number,
+            format,
+        }
+    }
+
+    /// Create a new comparison patch resource
+    pub fn compare(
+        owner: String,
+        repo: String,
+        base: String,
+        head: String,
+        format: PatchFormat,
+    ) -> Self {
+        Resource::Compare {
+            owner,
+            repo,
+            base,
+            head,
+            format,
+        }
+    }
+
+    /// Create a new REST API resource
+    pub fn api(owner: String, repo: String, endpoint: ApiEndpoint) -> Self {
+        Resource::Api {
+            owner,
+            repo,
+            endpoint,
+        }
+    }
+
+    /// Create a new container image resource
+    pub fn image(registry: String, name: String, reference: String) -> Self {
+        Resource::Image {
+            registry,
+            name,
+            reference,
+        }
+    }
+
+    /// Names returned by [`Resource::kind`]
+    pub const KINDS: [&'static str; 10] = [
+        "file",
+        "release",
+        "archive",
+        "repository",
+        "tree",
+        "commit",
+        "pull",
+        "compare",
+        "api",
+        "image",
+    ];
+
+    /// Short name of the resource type, e.g. `release`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Resource::File { .. } => "file",
+            Resource::Release { .. } => "release",
+            Resource::Archive { .. } => "archive",
+            Resource::Repository { .. } => "repository",
+            Resource::Tree { .. } => "tree",
+            Resource::Commit { .. } => "commit",
+            Resource::PullRequest { .. } => "pull",
+            Resource::Compare { .. } => "compare",
+            Resource::Api { .. } => "api",
+            Resource::Image { .. } => "image",
+        }
+    }
+
+    /// Git reference the resource is taken from, the tag for release assets
+    pub fn reference(&self) -> Option<&str> {
+        match self {
+            Resource::File { reference, .. }
+            | Resource::Archive { reference, .. }
+            | Resource::Tree { reference, .. }
+            | Resource::Image { reference, .. } => Some(reference),
+            Resource::Release { tag, .. } => Some(tag),
+            Resource::Commit { sha, .. } => Some(sha),
+            Resource::Api { endpoint, .. } => match endpoint {
+                ApiEndpoint::LatestRelease => None,
+                ApiEndpoint::Release { tag } => Some(tag),
+                ApiEndpoint::Contents { reference, .. } => reference.as_deref(),
+                ApiEndpoint::GitTree { reference, .. } => Some(reference),
+            },
+            Resource::Repository { .. }
+            | Resource::PullRequest { .. }
+            | Resource::Compare { .. } => None,
+        }
+    }
+
+    /// Kind of the reference the resource is taken from, None for whole repositories
+    /// and images, whose references aren't git references
+    pub fn reference_kind(&self) -> Option<ReferenceKind> {
+        match self {
+            // Releases are always created from a tag
+            Resource::Release { .. } => Some(ReferenceKind::Tag),
+            Resource::Image { .. } => None,
+            _ => self.reference().map(ReferenceKind::of),
+        }
+    }
+
+    /// Whether the content behind the resource can never change
+    ///
+    /// Release assets are treated as immutable once published under a
+    /// version tag such as `v1.2.3`, images when pinned to a digest, other
+    /// resources only when pinned to full 40 digit commit SHAs. Branches,
+    /// tags like `nightly`, abbreviated SHAs and pull requests can move, and
+    /// API responses carry changing metadata.
+    pub fn is_immutable(&self) -> bool {
+        let is_full_sha = |reference: &str| {
+            reference.len() == 40 && ReferenceKind::of(reference) == ReferenceKind::CommitSha
+        };
+        match self {
+            Resource::Release { tag, .. } => {
+                let version = tag.strip_prefix('v').unwrap_or(tag);
+                version.starts_with(|c: char| c.is_ascii_digit())
+            }
+            Resource::Api { .. } => false,
+            Resource::Image { reference, .. } => reference.starts_with("sha256:"),
+            Resource::Compare { base, head, .. } => [base, head].iter().all(|r| is_full_sha(r)),
+            _ => self.reference().is_some_and(is_full_sha),
+        }
+    }
+
+    /// Owner of the repository the resource belongs to, the first component
+    /// of an image name
+    pub fn owner(&self) -> &str {
+        match self {
+            Resource::File { owner, .. }
+            | Resource::Release { owner, .. }
+            | Resource::Archive { owner, .. }
+            | Resource::Repository { owner, .. }
+            | Resource::Tree { owner, .. }
+            | Resource::Commit { owner, .. }
+            | Resource::PullRequest { owner, .. }
+            | Resource::Compare { owner, .. }
+            | Resource::Api { owner, .. } => owner,
+            Resource::Image { name, .. } => name.split_once('/').map_or("", |(owner, _)| owner),
+        }
+    }
+
+    /// Name of the repository the resource belongs to, the rest of an image name
+    pub fn repo(&self) -> &str {
+        match self {
+            Resource::File { repo, .. }
+            | Resource::Release { repo, .. }
+            | Resource::Archive { repo, .. }
+            | Resource::Repository { repo, .. }
+            | Resource::Tree { repo, .. }
+            | Resource::Commit { repo, .. }
+            | Resource::PullRequest { repo, .. }
+            | Resource::Compare { repo, .. }
+            | Resource::Api { repo, .. } => repo,
+            Resource::Image { name, .. } => name.split_once('/').map_or(name, |(_, repo)| repo),
+        }
+    }
+
     /// Convert the resource to a proxied URL
     ///
     /// Returns None if the proxy type doesn't support the resource type
     /// (e.g., jsdelivr doesn't support release assets from /releases/download/)
+    ///
+    /// References that read as version ranges, like `1.x`, are only resolved
+    /// by jsdelivr. Other proxies take them literally as branch or tag names.
+    /// Images have no URL, see [`Resource::pull_reference`].
     pub fn url(&self, proxy_type: &Proxy) -> Option<String> {
         match self {
             Resource::File {
@@ -118,7 +566,249 @@ impl Resource {
                 // statically doesn't support release assets from /releases/download/
                 Proxy::Statically => None,
             },
+            Resource::Archive {
+                owner,
+                repo,
+                reference,
+                format,
+            } => match proxy_type {
+                Proxy::Github => Some(format!(
+                    "https://github.com/{}/{}/archive/{}.{}",
+                    owner,
+                    repo,
+                    reference,
+                    format.extension()
+                )),
+                Proxy::Xget => Some(format!(
+                    "https://xget.xi-xu.me/gh/{}/{}/archive/{}.{}",
+                    owner,
+                    repo,
+                    reference,
+                    format.extension()
+                )),
+                Proxy::GhProxy => Some(format!(
+                    "https://gh-proxy.com/https://github.com/{}/{}/archive/{}.{}",
+                    owner,
+                    repo,
+                    reference,
+                    format.extension()
+                )),
+                // CDNs serve repository files, not generated archives
+                Proxy::Jsdelivr | Proxy::Statically => None,
+            },
+            Resource::Repository { owner, repo } => proxy_type
+                .clone_prefix()
+                .map(|prefix| format!("{}{}/{}", prefix, owner, repo)),
+            Resource::Tree {
+                owner,
+                repo,
+                reference,
+                path,
+            } => match proxy_type {
+                Proxy::Github | Proxy::GhProxy | Proxy::Xget => Resource::api(
+                    owner.clone(),
+                    repo.clone(),
+                    ApiEndpoint::Contents {
+                        path: path.clone(),
+                        reference: Some(reference.clone()),
+                    },
+                )
+                .url(proxy_type),
+                // The trailing slash asks for the listing instead of a file
+                Proxy::Jsdelivr => Some(
+                    format!(
+                        "https://cdn.jsdelivr.net/gh/{}/{}@{}/{}",
+                        owner, repo, reference, path
+                    )
+                    .trim_end_matches('/')
+                    .to_string()
+                        + "/",
+                ),
+                Proxy::Statically => None,
+            },
+            Resource::Commit {
+                owner,
+                repo,
+                sha,
+                format,
+            } => github_path(
+                proxy_type,
+                &format!("{}/{}/commit/{}.{}", owner, repo, sha, format.extension()),
+            ),
+            Resource::PullRequest {
+                owner,
+                repo,
+                number,
+                format,
+            } => github_path(
+                proxy_type,
+                &format!("{}/{}/pull/{}.{}", owner, repo, number, format.extension()),
+            ),
+            Resource::Compare {
+                owner,
+                repo,
+                base,
+                head,
+                format,
+            } => github_path(
+                proxy_type,
+                &format!(
+                    "{}/{}/compare/{}...{}.{}",
+                    owner,
+                    repo,
+                    base,
+                    head,
+                    format.extension()
+                ),
+            ),
+            Resource::Api {
+                owner,
+                repo,
+                endpoint,
+            } => api_path(
+                proxy_type,
+                &format!("{}/{}/{}", owner, repo, endpoint.path()),
+            ),
+            // Images are pulled by reference, see Resource::pull_reference
+            Resource::Image { .. } => None,
+        }
+    }
+
+    /// Reference to pull an image through `proxy_type`, e.g.
+    /// `xget.xi-xu.me/cr/ghcr/owner/app:1.0`
+    ///
+    /// Returns None for other resources, or if the proxy doesn't mirror the
+    /// registry.
+    pub fn pull_reference(&self, proxy_type: &Proxy) -> Option<String> {
+        let Resource::Image {
+            registry,
+            name,
+            reference,
+        } = self
+        else {
+            return None;
+        };
+        Some(image_reference(
+            &pull_prefix(proxy_type, registry)?,
+            name,
+            reference,
+        ))
+    }
+
+    /// Like [`Resource::url`], but with an error naming the unsupported combination
+    pub fn try_url(&self, proxy_type: &Proxy) -> Result<String, ConversionError> {
+        self.url(proxy_type)
+            .ok_or(ConversionError::UnsupportedResource {
+                proxy: *proxy_type,
+                kind: self.kind(),
+            })
+    }
+
+    /// jsdelivr URL that purges the resource from the CDN cache
+    ///
+    /// Purging makes jsdelivr pick up new commits of a branch, or a new
+    /// release matching a version range, right away instead of hours later.
+    pub fn purge_url(&self) -> Option<String> {
+        let url = self.url(&Proxy::Jsdelivr)?;
+        url.strip_prefix("https://cdn.jsdelivr.net/")
+            .map(|rest| format!("https://purge.jsdelivr.net/{}", rest))
+    }
+
+    /// The minified variant of a `.js` or `.css` file
+    ///
+    /// jsdelivr generates `<name>.min.js` and `<name>.min.css` on the fly
+    /// when the repository doesn't contain them. Other proxies only serve
+    /// minified files that are committed.
+    pub fn minified(&self) -> Option<Resource> {
+        let Resource::File {
+            owner,
+            repo,
+            reference,
+            path,
+        } = self
+        else {
+            return None;
+        };
+        let (stem, extension) = path.rsplit_once('.')?;
+        if !matches!(extension, "js" | "css") || stem.ends_with(".min") {
+            return None;
         }
+        Some(Resource::file(
+            owner.clone(),
+            repo.clone(),
+            reference.clone(),
+            format!("{}.min.{}", stem, extension),
+        ))
+    }
+
+    /// Reference to pull an image through a registry mirror such as `mirror.example.com`
+    ///
+    /// The mirror may include a path prefix, e.g. `xget.example.com/cr/ghcr`
+    /// for a self-hosted xget. Returns None for other resources.
+    pub fn mirrored_image(&self, mirror: &str) -> Option<String> {
+        let Resource::Image {
+            name, reference, ..
+        } = self
+        else {
+            return None;
+        };
+        let mirror = mirror.split_once("://").map_or(mirror, |(_, rest)| rest);
+        Some(image_reference(
+            mirror.trim_end_matches('/'),
+            name,
+            reference,
+        ))
+    }
+
+    /// `media.githubusercontent.com` URL serving the content of a file tracked by Git LFS
+    ///
+    /// `raw` URLs on github.com redirect there on their own. Proxies that
+    /// don't [resolve LFS](Proxy::resolves_lfs) serve the pointer file instead.
+    pub fn media_url(&self) -> Option<String> {
+        let Resource::File {
+            owner,
+            repo,
+            reference,
+            path,
+        } = self
+        else {
+            return None;
+        };
+        Some(format!(
+            "https://media.githubusercontent.com/media/{}/{}/{}/{}",
+            owner, repo, reference, path
+        ))
+    }
+}
+
+/// `registry/name:tag`, or `registry/name@digest`
+fn image_reference(registry: &str, name: &str, reference: &str) -> String {
+    let separator = if reference.contains(':') { '@' } else { ':' };
+    format!("{}/{}{}{}", registry, name, separator, reference)
+}
+
+/// URL of a github.com page path through proxies that forward any GitHub URL
+fn github_path(proxy_type: &Proxy, path: &str) -> Option<String> {
+    match proxy_type {
+        Proxy::Github => Some(format!("https://github.com/{}", path)),
+        Proxy::Xget => Some(format!("https://xget.xi-xu.me/gh/{}", path)),
+        Proxy::GhProxy => Some(format!("https://gh-proxy.com/https://github.com/{}", path)),
+        // CDNs only serve repository files
+        Proxy::Jsdelivr | Proxy::Statically => None,
+    }
+}
+
+/// URL of an `api.github.com/repos/` path through proxies that forward the API
+fn api_path(proxy_type: &Proxy, path: &str) -> Option<String> {
+    match proxy_type {
+        Proxy::Github => Some(format!("https://api.github.com/repos/{}", path)),
+        Proxy::GhProxy => Some(format!(
+            "https://gh-proxy.com/https://api.github.com/repos/{}",
+            path
+        )),
+        // xget forwards the API host under its own route, apart from /gh/
+        Proxy::Xget => Some(format!("https://xget.xi-xu.me/api/github/repos/{}", path)),
+        Proxy::Jsdelivr | Proxy::Statically => None,
     }
 }
 
@@ -145,6 +835,76 @@ fn blob_file_regex() -> &'static Regex {
     })
 }
 
+fn raw_githubusercontent_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^https?://raw\.githubusercontent\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/(?P<rest>.+)$",
+        )
+        .unwrap()
+    })
+}
+
+fn media_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^https?://media\.githubusercontent\.com/media/(?P<owner>[^/]+)/(?P<repo>[^/]+)/(?P<rest>.+)$",
+        )
+        .unwrap()
+    })
+}
+
+fn image_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // Name components are lowercase, separated by `.`, `_`, `__` or dashes
+        Regex::new(r"^(?P<registry>ghcr\.io)/(?P<name>[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*(?:/[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*)+)(?::(?P<tag>[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}))?(?:@(?P<digest>sha256:[0-9a-f]{64}))?$")
+            .unwrap()
+    })
+}
+
+fn archive_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^https?://github\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/archive/(?P<reference>.+)\.(?P<ext>tar\.gz|zip)$")
+            .unwrap()
+    })
+}
+
+fn repository_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^https?://github\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+?)(?:\.git)?/?$").unwrap()
+    })
+}
+
+fn tree_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^https?://github\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/tree/(?P<rest>.+?)/?$")
+            .unwrap()
+    })
+}
+
+fn api_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^https?://api\.github\.com/repos/(?P<owner>[^/]+)/(?P<repo>[^/]+)/(?P<endpoint>.+)$",
+        )
+        .unwrap()
+    })
+}
+
+fn patch_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^https?://github\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/(?:commit/(?P<sha>[0-9a-fA-F]{7,40})|pull/(?P<number>[0-9]+)|compare/(?P<base>.+?)\.\.\.(?P<head>.+?))\.(?P<ext>patch|diff)$")
+            .unwrap()
+    })
+}
+
 fn release_download_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
@@ -205,6 +965,121 @@ impl TryFrom<&str> for Resource {
             });
         }
 
+        // Try to match raw.githubusercontent.com URL: https://raw.githubusercontent.com/owner/repo/ref/path
+        if let Some(captures) = raw_githubusercontent_regex().captures(value) {
+            let (reference, path) = split_reference_and_path(&captures["rest"])?;
+
+            return Ok(Resource::File {
+                owner: captures["owner"].to_string(),
+                repo: captures["repo"].to_string(),
+                reference,
+                path,
+            });
+        }
+
+        // Try to match Git LFS media URL: https://media.githubusercontent.com/media/owner/repo/ref/path
+        if let Some(captures) = media_regex().captures(value) {
+            let (reference, path) = split_reference_and_path(&captures["rest"])?;
+
+            return Ok(Resource::File {
+                owner: captures["owner"].to_string(),
+                repo: captures["repo"].to_string(),
+                reference,
+                path,
+            });
+        }
+
+        // Try to match archive URL: https://github.com/owner/repo/archive/ref.{zip,tar.gz}
+        if let Some(captures) = archive_regex().captures(value) {
+            let format = match &captures["ext"] {
+                "zip" => ArchiveFormat::Zip,
+                _ => ArchiveFormat::TarGz,
+            };
+            return Ok(Resource::Archive {
+                owner: captures["owner"].to_string(),
+                repo: captures["repo"].to_string(),
+                reference: captures["reference"].to_string(),
+                format,
+            });
+        }
+
+        // Try to match directory URL: https://github.com/owner/repo/tree/ref/dir
+        if let Some(captures) = tree_regex().captures(value) {
+            let (reference, path) = split_reference_and_dir(&captures["rest"])?;
+
+            return Ok(Resource::Tree {
+                owner: captures["owner"].to_string(),
+                repo: captures["repo"].to_string(),
+                reference,
+                path,
+            });
+        }
+
+        // Try to match REST API URL: https://api.github.com/repos/owner/repo/releases/latest
+        if let Some(captures) = api_regex().captures(value) {
+            let endpoint = ApiEndpoint::parse(&captures["endpoint"])
+                .ok_or_else(|| ConversionError::InvalidUrl(value.to_string()))?;
+            return Ok(Resource::api(
+                captures["owner"].to_string(),
+                captures["repo"].to_string(),
+                endpoint,
+            ));
+        }
+
+        // Try to match patch URLs: https://github.com/owner/repo/{commit/sha,pull/1,compare/a...b}.{patch,diff}
+        if let Some(captures) = patch_regex().captures(value) {
+            let owner = captures["owner"].to_string();
+            let repo = captures["repo"].to_string();
+            let format = match &captures["ext"] {
+                "diff" => PatchFormat::Diff,
+                _ => PatchFormat::Patch,
+            };
+            if let Some(sha) = captures.name("sha") {
+                return Ok(Resource::commit(
+                    owner,
+                    repo,
+                    sha.as_str().to_string(),
+                    format,
+                ));
+            }
+            if let Some(number) = captures.name("number") {
+                let number = number
+                    .as_str()
+                    .parse()
+                    .map_err(|_| ConversionError::InvalidUrl(value.to_string()))?;
+                return Ok(Resource::pull_request(owner, repo, number, format));
+            }
+            return Ok(Resource::compare(
+                owner,
+                repo,
+                captures["base"].to_string(),
+                captures["head"].to_string(),
+                format,
+            ));
+        }
+
+        // Try to match image reference: ghcr.io/owner/image:tag or ghcr.io/owner/image@sha256:digest
+        if let Some(captures) = image_regex().captures(value) {
+            // A digest pins the image even when a tag is given too
+            let reference = captures
+                .name("digest")
+                .or_else(|| captures.name("tag"))
+                .map_or("latest", |reference| reference.as_str());
+            return Ok(Resource::image(
+                captures["registry"].to_string(),
+                captures["name"].to_string(),
+                reference.to_string(),
+            ));
+        }
+
+        // Try to match repository URL: https://github.com/owner/repo(.git)
+        if let Some(captures) = repository_regex().captures(value) {
+            return Ok(Resource::Repository {
+                owner: captures["owner"].to_string(),
+                repo: captures["repo"].to_string(),
+            });
+        }
+
         Err(ConversionError::InvalidUrl(value.to_string()))
     }
 }
@@ -246,6 +1121,18 @@ fn split_reference_and_path(rest: &str) -> Result<(String, String), ConversionEr
     }
 }
 
+/// Like [`split_reference_and_path`], but the path may be empty for the root
+fn split_reference_and_dir(rest: &str) -> Result<(String, String), ConversionError> {
+    let parts = if rest.starts_with("refs/") { 3 } else { 1 };
+    if rest.is_empty() || rest.split('/').count() < parts {
+        return Err(ConversionError::ParseError("Missing reference".to_string()));
+    }
+    if rest.split('/').count() == parts {
+        return Ok((rest.to_string(), String::new()));
+    }
+    split_reference_and_path(rest)
+}
+
 impl TryFrom<String> for Resource {
     type Error = ConversionError;
 
@@ -253,3 +1140,73 @@ impl TryFrom<String> for Resource {
         Resource::try_from(value.as_str())
     }
 }
+
+impl Resource {
+    /// Parse a canonical GitHub URL or a URL produced by one of the proxies
+    ///
+    /// Returns the proxy the URL was rendered for together with the resource,
+    /// so `resource.url(&Proxy::Github)` restores the canonical URL. Files
+    /// are always rendered as `raw` downloads, so a `blob` page comes back
+    /// as the `raw` URL of the same file.
+    pub fn from_proxied_url(url: &str) -> Result<(Proxy, Resource), ConversionError> {
+        let url = url.trim();
+
+        if let Some(inner) = url.strip_prefix("https://gh-proxy.com/") {
+            return Ok((Proxy::GhProxy, Resource::try_from(inner)?));
+        }
+
+        if let Some(rest) = url.strip_prefix("https://xget.xi-xu.me/gh/") {
+            let github = format!("https://github.com/{}", rest);
+            return Ok((Proxy::Xget, Resource::try_from(github)?));
+        }
+
+        // xget forwards api.github.com under its own route
+        if let Some(rest) = url.strip_prefix("https://xget.xi-xu.me/api/github/") {
+            let api = format!("https://api.github.com/{}", rest);
+            return Ok((Proxy::Xget, Resource::try_from(api)?));
+        }
+
+        // xget.xi-xu.me/cr/ghcr/owner/image:tag
+        if let Some(rest) = url.strip_prefix("xget.xi-xu.me/cr/")
+            && let Some((route, image)) = rest.split_once('/')
+            && let Some(registry) = xget_registry(route)
+        {
+            let image = format!("{}/{}", registry, image);
+            return Ok((Proxy::Xget, Resource::try_from(image.as_str())?));
+        }
+
+        // https://cdn.jsdelivr.net/gh/owner/repo@ref/path
+        if let Some(rest) = url.strip_prefix("https://cdn.jsdelivr.net/gh/") {
+            let invalid = || ConversionError::InvalidUrl(url.to_string());
+            let (owner, rest) = rest.split_once('/').ok_or_else(invalid)?;
+            let (repo, rest) = rest.split_once('@').ok_or_else(invalid)?;
+            // A trailing slash lists the directory
+            if let Some(dir) = rest.strip_suffix('/') {
+                let (reference, path) = split_reference_and_dir(dir)?;
+                return Ok((
+                    Proxy::Jsdelivr,
+                    Resource::tree(owner.to_string(), repo.to_string(), reference, path),
+                ));
+            }
+            let (reference, path) = split_reference_and_path(rest)?;
+            return Ok((
+                Proxy::Jsdelivr,
+                Resource::file(owner.to_string(), repo.to_string(), reference, path),
+            ));
+        }
+
+        // https://cdn.statically.io/gh/owner/repo/ref/path
+        if let Some(rest) = url.strip_prefix("https://cdn.statically.io/gh/") {
+            let invalid = || ConversionError::InvalidUrl(url.to_string());
+            let (owner, rest) = rest.split_once('/').ok_or_else(invalid)?;
+            let (repo, rest) = rest.split_once('/').ok_or_else(invalid)?;
+            let (reference, path) = split_reference_and_path(rest)?;
+            return Ok((
+                Proxy::Statically,
+                Resource::file(owner.to_string(), repo.to_string(), reference, path),
+            ));
+        }
+
+        Ok((Proxy::Github, Resource::try_from(url)?))
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{Proxy, Resource};
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Any http(s) URL up to whitespace, quotes or closing brackets
+fn url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"https?://[^ \t\r\n"'`<>()\[\]{}\\]+"#).unwrap())
+}
+
+/// Rewrite every GitHub URL in `text` to its proxied form
+///
+/// Raw, raw.githubusercontent.com, release, archive, patch and REST API URLs
+/// are converted, and `blob` links become the proxied `raw` download of the
+/// file. URLs that cannot be parsed, or that the proxy doesn't support, are
+/// left untouched. Repository and directory links are usually meant for
+/// people rather than tools, so they are left untouched as well. Git LFS
+/// media URLs are only converted for proxies that resolve LFS.
+pub fn rewrite_text(text: &str, proxy: &Proxy) -> String {
+    replace_urls(text, |url| match Resource::try_from(url).ok()? {
+        _ if url.contains("://media.githubusercontent.com/") && !proxy.resolves_lfs() => None,
+        Resource::Repository { .. } | Resource::Tree { .. } => None,
+        resource => resource.url(proxy),
+    })
+}
+
+/// Replace every URL in `text` for which `f` returns a new value
+///
+/// Trailing punctuation such as `.` or `,` is not treated as part of the URL,
+/// and URLs nested inside another URL (e.g. `https://gh-proxy.com/https://...`)
+/// are only seen as a whole.
+pub(crate) fn replace_urls<F>(text: &str, mut f: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for m in url_regex().find_iter(text) {
+        let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        let start = m.start();
+        let end = start + url.len();
+
+        if let Some(new) = f(url) {
+            out.push_str(&text[last..start]);
+            out.push_str(&new);
+            last = end;
+        }
+    }
+
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Lines of context around each change in [`rewrite_diff`]
+const DIFF_CONTEXT: usize = 3;
+
+/// Render a unified diff between two versions of a file
+///
+/// Rewriting replaces URLs in place and never moves lines, so line `i` of
+/// `old` is compared with line `i` of `new` instead of searching for moved
+/// lines. Changes closer than twice the context share a hunk. Returns an
+/// empty string when nothing changed.
+pub fn rewrite_diff(path: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let len = old.len().max(new.len());
+    let changed: Vec<usize> = (0..len).filter(|&i| old.get(i) != new.get(i)).collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Group changed lines into hunks of [start, end) including context
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(DIFF_CONTEXT);
+        let end = (i + 1 + DIFF_CONTEXT).min(len);
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", path, path);
+    for (start, end) in hunks {
+        let count = |lines: &[&str]| end.min(lines.len()).saturating_sub(start);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(start, count(&old)),
+            hunk_range(start, count(&new))
+        ));
+        let mut i = start;
+        while i < end {
+            if old.get(i) == new.get(i) {
+                out.push_str(&format!(" {}\n", old[i]));
+                i += 1;
+                continue;
+            }
+            // A run of changed lines shows all removals before the additions
+            let run = (i..end).take_while(|&j| old.get(j) != new.get(j)).count();
+            for line in old.iter().skip(i).take(run) {
+                out.push_str(&format!("-{}\n", line));
+            }
+            for line in new.iter().skip(i).take(run) {
+                out.push_str(&format!("+{}\n", line));
+            }
+            i += run;
+        }
+    }
+    out
+}
+
+/// `start,count` of a hunk header, 1-based
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        1 => format!("{}", start + 1),
+        // An empty range names the line before it
+        0 => format!("{},0", start),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// A URL replaced by one of the file rewriters
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Change {
+    /// 1-based line number
+    pub line: usize,
+    pub from: String,
+    pub to: String,
+}
+
+/// Re-target a canonical or already proxied GitHub URL to `proxy`
+///
+/// Returns None when the URL is not recognised, the proxy doesn't support
+/// the resource, or the URL is already in the requested form. A `blob` link
+/// is retargeted to the `raw` download of the file, and stays a `raw` URL
+/// when retargeted back to GitHub. Repository and directory links are left
+/// alone as in [`rewrite_text`], since their proxied forms don't map back to
+/// the same page.
+pub(crate) fn retarget_url(url: &str, proxy: &Proxy) -> Option<String> {
+    let (current, resource) = Resource::from_proxied_url(url).ok()?;
+    if current == *proxy {
+        return None;
+    }
+    match resource {
+        Resource::Repository { .. } | Resource::Tree { .. } => None,
+        resource => resource.url(proxy),
+    }
+}
+
+/// Re-target a repository clone URL to `proxy`
+///
+/// Like [`retarget_url`], but only for repository URLs, which keep their
+/// `.git` suffix in both directions.
+pub(crate) fn retarget_clone_url(url: &str, proxy: &Proxy) -> Option<String> {
+    let (current, resource @ Resource::Repository { .. }) = Resource::from_proxied_url(url).ok()?
+    else {
+        return None;
+    };
+    if current == *proxy {
+        return None;
+    }
+    let new = resource.url(proxy)?;
+    if url.trim_end_matches('/').ends_with(".git") {
+        Some(format!("{}.git", new))
+    } else {
+        Some(new)
+    }
+}
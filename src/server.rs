@@ -0,0 +1,367 @@
+use crate::policy::Limiter;
+use crate::{ConversionError, Policy, Proxy, ProxyChain, Resource};
+use std::str::FromStr as _;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Header clients can set to pick a proxy per request
+pub const PROXY_HEADER: &str = "X-Github-Proxy";
+
+/// Response headers passed through from upstream when streaming
+#[cfg(feature = "download")]
+const FORWARDED_HEADERS: [&str; 8] = [
+    "Content-Type",
+    "Content-Range",
+    "Accept-Ranges",
+    "ETag",
+    "Last-Modified",
+    "Content-Disposition",
+    "Cache-Control",
+    "Expires",
+];
+
+/// Threads answering requests, further clients wait for one to be free
+const WORKERS: usize = 16;
+
+/// Request headers passed on to upstream when streaming
+#[cfg(feature = "download")]
+const UPSTREAM_HEADERS: [&str; 3] = ["Range", "If-None-Match", "If-Modified-Since"];
+
+/// How the server answers requests
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ServerConfig {
+    /// Proxy used when a request doesn't pick one
+    pub proxy: Proxy,
+    /// Fetch upstream content and stream it back instead of redirecting
+    pub stream: bool,
+    /// Origins used to reach upstream when streaming
+    pub chain: ProxyChain,
+    /// Largest body streamed back, larger ones are refused with 413 and
+    /// ones of unknown length with 502 unless they come from the cache
+    pub max_size: Option<u64>,
+    /// Resources refused with 403 and per-client rate limit
+    pub policy: Policy,
+    /// Serve complete streamed bodies from this cache
+    #[cfg(feature = "download")]
+    pub cache: Option<crate::Cache>,
+}
+
+impl ServerConfig {
+    /// Redirect to `proxy`
+    pub fn new(proxy: Proxy) -> Self {
+        ServerConfig {
+            proxy,
+            stream: false,
+            chain: ProxyChain::new(Vec::new()),
+            max_size: None,
+            policy: Policy::default(),
+            #[cfg(feature = "download")]
+            cache: None,
+        }
+    }
+}
+
+/// Answer to a single request
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reply {
+    pub status: u16,
+    /// Redirect target for 3xx replies
+    pub location: Option<String>,
+    pub body: String,
+}
+
+impl Reply {
+    fn error(status: u16, body: impl Into<String>) -> Reply {
+        Reply {
+            status,
+            location: None,
+            body: body.into(),
+        }
+    }
+}
+
+/// Resolve a GitHub shaped request path to a redirect to the proxied URL
+///
+/// `target` is the request path with an optional query, e.g.
+/// `/owner/repo/raw/main/install.sh?proxy=xget`. The proxy is taken from the
+/// `proxy` query parameter, then the [`PROXY_HEADER`] header, then `default`.
+pub fn redirect(target: &str, header: Option<&str>, default: &Proxy) -> Reply {
+    match parse_target(target, header, default) {
+        Ok((resource, proxy)) => locate(&resource, &proxy),
+        Err(reply) => reply,
+    }
+}
+
+fn locate(resource: &Resource, proxy: &Proxy) -> Reply {
+    match resource.try_url(proxy) {
+        Ok(url) => Reply {
+            status: 302,
+            location: Some(url.clone()),
+            body: url,
+        },
+        Err(e) => Reply::error(400, e.to_string()),
+    }
+}
+
+/// Parse the resource and the proxy to use from a request
+pub(crate) fn parse_target(
+    target: &str,
+    header: Option<&str>,
+    default: &Proxy,
+) -> Result<(Resource, Proxy), Reply> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    // Upstream would resolve these to another repository
+    if crate::policy::has_dot_segment(path) {
+        return Err(Reply::error(
+            400,
+            "Paths with . or .. segments are not allowed",
+        ));
+    }
+    let requested = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("proxy="))
+        .or(header);
+    let proxy = match requested {
+        Some(name) => Proxy::from_str(name).map_err(|e| Reply::error(400, e.to_string()))?,
+        None => *default,
+    };
+
+    let resource = Resource::try_from(format!("https://github.com{}", path))
+        .map_err(|_| Reply::error(404, format!("Not a GitHub resource path: {}", path)))?;
+    Ok((resource, proxy))
+}
+
+/// Listen on `addr` and answer requests according to `config`
+///
+/// Blocks for as long as the server runs.
+pub fn serve(addr: &str, config: ServerConfig) -> Result<(), ConversionError> {
+    let server = tiny_http::Server::http(addr).map_err(|e| {
+        ConversionError::InvalidArguments(format!("cannot listen on {}: {}", addr, e))
+    })?;
+    run(server, config);
+    Ok(())
+}
+
+pub(crate) fn run(server: tiny_http::Server, config: ServerConfig) {
+    let state = Arc::new((server, config, Limiter::default()));
+    // Streamed bodies can take long, don't block other clients
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let state = state.clone();
+            std::thread::spawn(move || {
+                while let Ok(request) = state.0.recv() {
+                    handle(request, &state.1, &state.2);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+fn handle(request: tiny_http::Request, config: &ServerConfig, limiter: &Limiter) {
+    if let (Some(limit), Some(client)) = (&config.policy.rate_limit, request.remote_addr())
+        && let Err(wait) = limiter.check(limit, client.ip(), Instant::now())
+    {
+        let retry = wait.as_secs().max(1).to_string();
+        let mut reply = response(Reply::error(429, "Too many requests"));
+        if let Ok(header) = tiny_http::Header::from_bytes("Retry-After", retry) {
+            reply.add_header(header);
+        }
+        let _ = request.respond(reply);
+        return;
+    }
+
+    let header = header(&request, PROXY_HEADER);
+    let (resource, proxy) = match parse_target(request.url(), header.as_deref(), &config.proxy) {
+        Ok(parsed) => parsed,
+        Err(reply) => {
+            let _ = request.respond(response(reply));
+            return;
+        }
+    };
+    if let Err(reason) = config.policy.check(&resource) {
+        let _ = request.respond(response(Reply::error(403, reason)));
+        return;
+    }
+
+    #[cfg(feature = "download")]
+    if config.stream {
+        return stream(request, &resource, &proxy, config);
+    }
+
+    let _ = request.respond(response(locate(&resource, &proxy)));
+}
+
+/// Fetch the resource from upstream and stream the body back
+#[cfg(feature = "download")]
+fn stream(request: tiny_http::Request, resource: &Resource, proxy: &Proxy, config: &ServerConfig) {
+    use tiny_http::Method;
+
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        let _ = request.respond(response(Reply::error(405, "Method not allowed")));
+        return;
+    }
+    let Some(url) = config.chain.url(proxy, resource) else {
+        let unsupported = ConversionError::UnsupportedResource {
+            proxy: *proxy,
+            kind: resource.kind(),
+        };
+        let _ = request.respond(response(Reply::error(400, unsupported.to_string())));
+        return;
+    };
+
+    let agent = crate::download::agent(&config.chain);
+    if let Some(cache) = &config.cache
+        && *request.method() == Method::Get
+        && header(&request, "Range").is_none()
+    {
+        return cached(request, cache, &agent, resource, (*proxy, url), config);
+    }
+
+    let mut upstream = agent.request(request.method().as_str(), &url);
+    for name in UPSTREAM_HEADERS {
+        if let Some(value) = request
+            .headers()
+            .iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        {
+            upstream = upstream.set(name, value.value.as_str());
+        }
+    }
+
+    let upstream = match upstream.call() {
+        Ok(upstream) => upstream,
+        // Pass upstream errors such as 404 or 416 through
+        Err(ureq::Error::Status(code, _)) => {
+            let _ = request.respond(response(Reply::error(
+                code,
+                format!("Upstream returned {}", code),
+            )));
+            return;
+        }
+        Err(e) => {
+            let reply = Reply::error(502, crate::download::describe(e));
+            let _ = request.respond(response(reply));
+            return;
+        }
+    };
+
+    let length: Option<u64> = upstream
+        .header("Content-Length")
+        .and_then(|len| len.parse().ok());
+    match (config.max_size, length) {
+        (Some(max), Some(length)) if length > max => {
+            let _ = request.respond(response(too_large(length, max)));
+            return;
+        }
+        // A streamed body can't be cut off once it started, and buffering it
+        // would hold up to the limit in memory for every worker
+        (Some(max), None) => {
+            let reply = Reply::error(
+                502,
+                format!(
+                    "Upstream sent no Content-Length to check against the limit of {}",
+                    max
+                ),
+            );
+            let _ = request.respond(response(reply));
+            return;
+        }
+        _ => {}
+    }
+
+    let headers = FORWARDED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = upstream.header(name)?;
+            tiny_http::Header::from_bytes(*name, value).ok()
+        })
+        .collect();
+    let status = upstream.status();
+    let reply = tiny_http::Response::new(
+        status.into(),
+        headers,
+        upstream.into_reader(),
+        length.map(|len| len as usize),
+        None,
+    );
+    let _ = request.respond(reply);
+}
+
+/// Answer a request from the cache, filling it from upstream first if needed
+///
+/// Error statuses of upstream are passed through, and bodies beyond
+/// `max_size` are neither stored nor served.
+#[cfg(feature = "download")]
+fn cached(
+    request: tiny_http::Request,
+    cache: &crate::Cache,
+    agent: &ureq::Agent,
+    resource: &Resource,
+    (proxy, url): (Proxy, String),
+    config: &ServerConfig,
+) {
+    let cached = match cache.fetch_via(resource, agent, (proxy, &url), config.max_size) {
+        Ok(cached) => cached,
+        Err(e) => {
+            let status = match e {
+                ConversionError::UpstreamStatus(status) => status,
+                ConversionError::TooLarge(_) => 413,
+                _ => 502,
+            };
+            let _ = request.respond(response(Reply::error(status, e.to_string())));
+            return;
+        }
+    };
+    let file = match std::fs::File::open(&cached.path) {
+        Ok(file) => file,
+        Err(e) => {
+            let _ = request.respond(response(Reply::error(502, e.to_string())));
+            return;
+        }
+    };
+
+    // Entries stored before the limit was lowered
+    let length = file.metadata().map_or(0, |m| m.len());
+    if let Some(max) = config.max_size
+        && length > max
+    {
+        let _ = request.respond(response(too_large(length, max)));
+        return;
+    }
+    let mut reply = tiny_http::Response::from_file(file);
+    for (name, value) in &cached.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            reply.add_header(header);
+        }
+    }
+    let _ = request.respond(reply);
+}
+
+#[cfg(feature = "download")]
+fn too_large(length: u64, max: u64) -> Reply {
+    Reply::error(
+        413,
+        format!("Body of {} bytes exceeds the limit of {}", length, max),
+    )
+}
+
+pub(crate) fn header(request: &tiny_http::Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.to_string())
+}
+
+pub(crate) fn response(reply: Reply) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let mut response = tiny_http::Response::from_string(reply.body).with_status_code(reply.status);
+    if let Some(location) = reply.location
+        && let Ok(header) = tiny_http::Header::from_bytes("Location", location)
+    {
+        response.add_header(header);
+    }
+    response
+}
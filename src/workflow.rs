@@ -0,0 +1,106 @@
+use crate::Proxy;
+use crate::rewrite::{Change, replace_urls, retarget_url};
+
+/// Rewrite GitHub URLs in a GitHub Actions workflow
+///
+/// Only `run:` scripts (inline or block scalars) and the string inputs of
+/// `with:` mappings are touched. The file is processed line by line, so
+/// comments, quoting and indentation are preserved exactly.
+pub fn rewrite_workflow(text: &str, proxy: &Proxy) -> (String, Vec<Change>) {
+    let mut changes = Vec::new();
+    let mut out = String::with_capacity(text.len());
+    // Column of the key owning the block scalar we are in
+    let mut block: Option<usize> = None;
+    // Column of the `with:` key whose inputs we are in
+    let mut with: Option<usize> = None;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let trimmed = content.trim_start();
+        let indent = content.len() - trimmed.len();
+        let blank = trimmed.is_empty();
+
+        let mut rewrite = |s: &str| {
+            replace_urls(s, |url| {
+                let new = retarget_url(url, proxy)?;
+                changes.push(Change {
+                    line: i + 1,
+                    from: url.to_string(),
+                    to: new.clone(),
+                });
+                Some(new)
+            })
+        };
+
+        if let Some(column) = block {
+            if blank || indent > column {
+                out.push_str(&rewrite(line));
+                continue;
+            }
+            block = None;
+        }
+
+        if blank || trimmed.starts_with('#') {
+            out.push_str(line);
+            continue;
+        }
+
+        if with.is_some_and(|column| indent <= column) {
+            with = None;
+        }
+
+        let Some((column, key, value)) = split_key(content) else {
+            out.push_str(line);
+            continue;
+        };
+
+        if with.is_none() && key == "with" && value.trim().is_empty() {
+            with = Some(column);
+            out.push_str(line);
+            continue;
+        }
+
+        if with.is_some() || key == "run" {
+            if is_block_scalar(value) {
+                block = Some(column);
+                out.push_str(line);
+            } else {
+                // Keep trailing comments out of the rewritten value
+                let start = content.len() - value.len();
+                let end = value.find(" #").map_or(content.len(), |i| start + i);
+                out.push_str(&rewrite(&line[..end]));
+                out.push_str(&line[end..]);
+            }
+            continue;
+        }
+
+        out.push_str(line);
+    }
+
+    (out, changes)
+}
+
+/// Split a `key: value` line, skipping any leading `- ` sequence markers
+///
+/// Returns the column of the key, the key and the rest of the line after the colon.
+fn split_key(line: &str) -> Option<(usize, &str, &str)> {
+    let mut rest = line.trim_start();
+    while let Some(item) = rest.strip_prefix("- ") {
+        rest = item.trim_start();
+    }
+    let column = line.len() - rest.len();
+    let (key, value) = rest.split_once(':')?;
+    if key.is_empty() || key.contains([' ', '"', '\'']) {
+        return None;
+    }
+    if !(value.is_empty() || value.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((column, key, value))
+}
+
+fn is_block_scalar(value: &str) -> bool {
+    let value = value.trim();
+    let value = value.split(" #").next().unwrap_or_default();
+    value.starts_with('|') || value.starts_with('>')
+}